@@ -0,0 +1,313 @@
+//! Provides loading of images without knowing their format in advance.
+//!
+//! `open` and `decode` find the format from the magic number at the start of the data and load
+//! the image with the matching decoder. The returned `AnyImage` records the format and mode of
+//! the file along with the image data. `probe` reads only the header, which is much faster when
+//! just the size or format of an image is needed.
+//!
+//! # Examples
+//!
+//! ```
+//! use netbpm::{ImageType,Mode};
+//!
+//! let data:&[u8] = b"P2\n2 2\n15\n0 15\n15 0\n";
+//! let image = netbpm::decode(data).unwrap();
+//! assert_eq!(image.image_type, ImageType::PGM);
+//! assert_eq!(image.mode, Mode::ASCII);
+//! assert_eq!(image.max_val, 15);
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use rows::Rows;
+use tools::{get_header, is_number, is_whitespace, load_raster, load_raster_partial, ImageHeader, Stream};
+use BitDepth;
+use Comment;
+use Error;
+use ImageType;
+use Limits;
+use Mode;
+use PartialImage;
+
+/// An image loaded by `open` or `decode`.
+///
+/// The data is stored the same way as by the decoder for the format. pbm images have one byte per
+/// pixel, ppm images have three samples per pixel, and 16-bit samples are stored as two bytes with
+/// the most significant byte first.
+pub struct AnyImage {
+    /// The format of the file the image was loaded from.
+    pub image_type: ImageType,
+    /// Whether the image was stored in ASCII or binary format. pam images are always binary.
+    pub mode: Mode,
+    pub width:u32,
+    pub height:u32,
+    /// The number of samples in each pixel.
+    pub channels:u32,
+    pub dat: Vec<u8>,
+    pub depth: BitDepth,
+    /// The largest value a sample can have. For pbm images this is always 1.
+    pub max_val: u16,
+    /// The tuple type of a pam image. Empty for the other formats.
+    pub tuple_type: String,
+    /// The comments from the header of the file.
+    pub comments: Vec<Comment>,
+}
+
+/// Loads the first image in a file of any format.
+///
+/// # Examples
+///
+/// ```
+/// # use std::fs;
+/// # use std::fs::File;
+/// # use std::io::prelude::*;
+/// # let mut file = File::create("image_any.ppm").unwrap();
+/// # let _ = file.write(b"P3\n1 1\n255\n255 0 0\n");
+/// let image = netbpm::open("image_any.ppm").unwrap();
+/// assert_eq!(image.channels, 3);
+/// # let _ = fs::remove_file("image_any.ppm");
+/// ```
+///
+/// # Errors
+///
+/// Returns an `Error::Io` if the file can't be opened, as well as all of the errors from `decode`.
+pub fn open(file_name: &str) -> Result<AnyImage, Error> {
+    let file = File::open(file_name)?;
+    decode(file)
+}
+
+/// Loads the first image from any `Read`, whatever its format.
+///
+/// If the data holds more than one image only the first is loaded. Use an `AnyDecoder` to load
+/// the others.
+///
+/// # Errors
+///
+/// Returns `BadMagicNumber` if the data isn't a Netpbm image, `NoMoreImages` if it is empty, and
+/// all of the errors returned when reading the header.
+pub fn decode<R: Read>(reader: R) -> Result<AnyImage, Error> {
+    AnyDecoder::from_reader(reader).load()
+}
+
+/// Decodes images of any format.
+///
+/// Each image in the stream can be in a different format. Like the other decoders it can be used
+/// as an `Iterator` over the images.
+pub struct AnyDecoder<R: Read = File> {
+    stream: Stream<R>,
+}
+
+impl AnyDecoder<File> {
+    /// Create a new `AnyDecoder` that reads from the specified file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened.
+    pub fn new(file_name: &str) -> Result<AnyDecoder, Error> {
+        let file = File::open(file_name)?;
+        Ok(AnyDecoder{stream: Stream::new(file)})
+    }
+}
+
+impl<R: Read> AnyDecoder<R> {
+    /// Create a new `AnyDecoder` that reads from any `Read`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::AnyDecoder;
+    ///
+    /// let data:&[u8] = b"P1\n1 1\n1\nP2\n1 1\n255\n7\n";
+    /// let decoder = AnyDecoder::from_reader(data);
+    /// assert_eq!(decoder.count(), 2);
+    /// ```
+    pub fn from_reader(reader: R) -> AnyDecoder<R> {
+        AnyDecoder{stream: Stream::new(reader)}
+    }
+
+    /// Sets the limits on the size of images loaded after this.
+    ///
+    /// Images larger than the limits are rejected with `LimitExceeded` as soon as their header is
+    /// read. See `Limits` for details.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.stream.set_limits(limits);
+    }
+
+    /// Loads the next image, whatever its format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BadMagicNumber` if the data isn't a Netpbm image, `NoMoreImages` if there are no
+    /// images left, `Truncated` if the data ends before the end of the raster, and all of the
+    /// errors returned when reading the header.
+    pub fn load(&mut self) -> Result<AnyImage, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.header()?;
+        let dat = load_raster(&mut self.stream, &header)?;
+        Ok(AnyImage{image_type: header.image_type, mode: header.mode, width: header.width, height: header.height,
+                    channels: header.channels, dat, depth: header.depth, max_val: header.max_val,
+                    tuple_type: header.tuple_type, comments: header.comments})
+    }
+
+    /// Loads the next image, whatever its format, even if it ends part way through the raster.
+    ///
+    /// The missing samples of an image that was cut short are set to 0. See `PartialImage`.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for `Truncated` errors in the raster.
+    pub fn load_partial(&mut self) -> Result<PartialImage<AnyImage>, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.header()?;
+        let (dat, rows) = load_raster_partial(&mut self.stream, &header)?;
+        let complete = rows == header.height;
+        let image = AnyImage{image_type: header.image_type, mode: header.mode, width: header.width, height: header.height,
+                             channels: header.channels, dat, depth: header.depth, max_val: header.max_val,
+                             tuple_type: header.tuple_type, comments: header.comments};
+        Ok(PartialImage{image, rows, complete})
+    }
+
+    /// Starts loading the next image one row at a time, whatever its format.
+    ///
+    /// See the `rows` module for details.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same header errors as `load`. Errors in the raster are returned when the
+    /// row is read.
+    pub fn rows(&mut self) -> Result<Rows<'_, R>, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.header()?;
+        Ok(Rows::new(&mut self.stream, header))
+    }
+}
+
+impl<R: Read> Iterator for AnyDecoder<R> {
+    type Item = Result<AnyImage, Error>;
+
+    /// Loads the next image in the stream.
+    ///
+    /// If an image can't be loaded the error is returned and no more images will be read.
+    fn next(&mut self) -> Option<Result<AnyImage, Error>> {
+        match self.stream.at_end() {
+            Ok(true) => None,
+            Ok(false) => {
+                let image = self.load();
+                if image.is_err() {
+                    self.stream.finish();
+                }
+                Some(image)
+            },
+            Err(e) => {
+                self.stream.finish();
+                Some(Result::Err(Error::Io(e)))
+            },
+        }
+    }
+}
+
+/// The most bytes `probe` reads while looking for the end of a header.
+const MAX_HEADER_LEN: usize = 1 << 20;
+
+/// Reads the header of an image without reading any of the raster.
+///
+/// The data is read one byte at a time and reading stops at the end of the header, so nothing
+/// past the header is taken from the reader. This makes it safe to use on pipes and sockets, where
+/// the rest of the image can still be read afterwards. The `dat_start` of the returned header is
+/// the number of bytes that were read. Files should be wrapped in a `BufReader` if reading past
+/// the header doesn't matter, as this avoids a system call for every byte.
+///
+/// # Examples
+///
+/// ```
+/// use netbpm::ImageType;
+///
+/// let data:&[u8] = b"P5\n640 480\n255\n";
+/// let header = netbpm::probe(data).unwrap();
+/// assert_eq!(header.image_type, ImageType::PGM);
+/// assert_eq!((header.width, header.height), (640, 480));
+/// assert_eq!(header.dat_start, 15);
+/// ```
+///
+/// # Errors
+///
+/// Returns `NoMoreImages` if the reader is empty, `Truncated` if it ends before the end of the
+/// header, `LimitExceeded` if the header is longer than 1 MiB, and all of the other errors
+/// returned when reading a header.
+pub fn probe<R: Read>(mut reader: R) -> Result<ImageHeader, Error> {
+    let mut dat:Vec<u8> = vec![];
+    let mut end = HeaderEnd{values: 0, in_comment: false, line_start: 0};
+    let mut byte = [0u8; 1];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) => dat.push(byte[0]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Result::Err(Error::Io(e)),
+        }
+        if dat.len() > MAX_HEADER_LEN {
+            return Result::Err(Error::LimitExceeded{limit: "header size", value: dat.len() as u64, max: MAX_HEADER_LEN as u64});
+        }
+        // The magic number is checked straight away so that other files aren't read to the end.
+        // After that the header is only parsed where it could end.
+        if dat.len() == 2 || end.update(&dat) {
+            match get_header(&dat) {
+                Err(Error::Truncated) => {},
+                result => return result,
+            }
+        }
+    }
+    if dat.is_empty() {
+        return Result::Err(Error::NoMoreImages);
+    }
+    get_header(&dat)
+}
+
+/// Follows a header as it is read to find the places it could end.
+///
+/// This saves parsing the whole header again after every byte.
+struct HeaderEnd {
+    /// The number of values read outside of comments, counting the magic number.
+    values: u32,
+    in_comment: bool,
+    /// The index of the first character of the current line.
+    line_start: usize,
+}
+
+impl HeaderEnd {
+    /// Takes the last byte of `dat` and returns `true` if the header could end with it.
+    ///
+    /// pam headers end with the line holding `ENDHDR`. The other formats end with the whitespace
+    /// after the last value, which is the height for pbm images and the max value otherwise.
+    fn update(&mut self, dat: &[u8]) -> bool {
+        let c = dat[dat.len() - 1];
+        if dat.len() < 3 {
+            return false;
+        }
+        if dat[1] == b'7' {
+            let line = &dat[self.line_start..];
+            if c == b'\n' {
+                self.line_start = dat.len();
+                return line.windows(6).any(|w| w == b"ENDHDR");
+            }
+            return false;
+        }
+        if !self.in_comment && is_number(dat[dat.len() - 2]) && !is_number(c) {
+            self.values += 1;
+        }
+        if c == b'#' {
+            self.in_comment = true;
+        } else if c == b'\n' || c == b'\r' {
+            self.in_comment = false;
+        }
+        let needed = if dat[1] == b'1' || dat[1] == b'4' { 3 } else { 4 };
+        !self.in_comment && self.values >= needed && is_whitespace(c)
+    }
+}
@@ -0,0 +1,163 @@
+//! Converts Netpbm images between the plain and raw forms and between formats.
+//!
+//! Usage: `pnmconvert [OPTIONS] [INPUT [OUTPUT]]`
+//!
+//! Every image in the input is converted and written to the output, so files holding more than
+//! one image keep all of them. If no input is given the images are read from standard input, and
+//! if no output is given they are written to standard output, so the command can be used in a
+//! shell pipeline.
+
+extern crate netbpm;
+
+use netbpm::convert::Luminance;
+use netbpm::pbm::PBMEncoder;
+use netbpm::pgm::PGMEncoder;
+use netbpm::ppm::PPMEncoder;
+use netbpm::{AnyDecoder, AnyImage, Error, ImageType, Mode};
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+const USAGE: &str = "Usage: pnmconvert [OPTIONS] [INPUT [OUTPUT]]
+
+Converts every image in INPUT and writes it to OUTPUT. Reads from standard
+input if no INPUT is given, or if INPUT is -, and writes to standard output if
+no OUTPUT is given.
+
+Options:
+    --plain             write the plain (ASCII) form: P1, P2, or P3
+    --raw               write the raw (binary) form: P4, P5, or P6
+    --pbm               convert to a bitmap by thresholding the brightness
+    --pgm               convert to gray scale using the luminance of colors
+    --ppm               convert to color
+    --rec709            use the Rec. 709 weights for the luminance of colors
+                        instead of the Rec. 601 weights
+    --threshold VALUE   the brightness, from 0 to 1, below which pixels become
+                        black when converting to a bitmap. Defaults to 0.5
+    --help              print this message
+
+Without --plain or --raw each image keeps its form, and without a format
+option each image keeps its format.";
+
+/// The options given on the command line.
+struct Options {
+    mode: Option<Mode>,
+    image_type: Option<ImageType>,
+    luminance: Luminance,
+    threshold: f64,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options{mode: None, image_type: None, luminance: Luminance::REC601,
+                               threshold: 0.5, input: None, output: None};
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plain" => options.mode = Some(Mode::ASCII),
+            "--raw" => options.mode = Some(Mode::BINARY),
+            "--pbm" => options.image_type = Some(ImageType::PBM),
+            "--pgm" => options.image_type = Some(ImageType::PGM),
+            "--ppm" => options.image_type = Some(ImageType::PPM),
+            "--rec709" => options.luminance = Luminance::REC709,
+            "--threshold" => {
+                let value = args.next().ok_or("--threshold needs a value")?;
+                options.threshold = match value.parse::<f64>() {
+                    Ok(t) if (0.0..=1.0).contains(&t) => t,
+                    _ => return Result::Err(format!("the threshold must be between 0 and 1, not {}", value)),
+                };
+            },
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ if arg.starts_with("--") => return Result::Err(format!("unknown option {}", arg)),
+            _ if options.input.is_none() => options.input = Some(arg),
+            _ if options.output.is_none() => options.output = Some(arg),
+            _ => return Result::Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Writes an image with the encoder for its format.
+fn save<W: Write>(out: W, image: &AnyImage, mode: Mode) -> Result<(), Error> {
+    match image.image_type {
+        ImageType::PBM => {
+            let mut encoder = PBMEncoder::from_writer(out);
+            encoder.set_comments(&image.comments);
+            encoder.save(&image.dat, image.width, image.height, mode)
+        },
+        ImageType::PGM => {
+            let mut encoder = PGMEncoder::from_writer(out);
+            encoder.set_comments(&image.comments);
+            encoder.save_max_val(&image.dat, image.width, image.height, mode, image.max_val)
+        },
+        ImageType::PPM => {
+            let mut encoder = PPMEncoder::from_writer(out);
+            encoder.set_comments(&image.comments);
+            encoder.save_max_val(&image.dat, image.width, image.height, mode, image.max_val)
+        },
+        ImageType::PAM => Result::Err(Error::InvalidParameter("pam images can not be converted.")),
+    }
+}
+
+/// Converts every image from `input` and writes it to `out`.
+fn run<R: Read, W: Write>(input: R, mut out: W, options: &Options) -> Result<(), Error> {
+    let decoder = AnyDecoder::from_reader(input);
+    for image in decoder {
+        let image = image?;
+        let mode = options.mode.unwrap_or(image.mode);
+        let image = match options.image_type {
+            Some(ImageType::PBM) => image.to_pbm(options.luminance, options.threshold)?,
+            Some(ImageType::PGM) => image.to_pgm(options.luminance)?,
+            Some(ImageType::PPM) => image.to_ppm()?,
+            _ => image,
+        };
+        save(&mut out, &image, mode)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("pnmconvert: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let input: Box<dyn Read> = match options.input {
+        Some(ref name) if name != "-" => match File::open(name) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("pnmconvert: {}: {}", name, e);
+                process::exit(1);
+            },
+        },
+        _ => Box::new(stdin.lock()),
+    };
+    let output: Box<dyn Write> = match options.output {
+        Some(ref name) => match File::create(name) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(e) => {
+                eprintln!("pnmconvert: {}: {}", name, e);
+                process::exit(1);
+            },
+        },
+        None => Box::new(BufWriter::new(stdout.lock())),
+    };
+
+    if let Err(e) = run(input, output, &options) {
+        eprintln!("pnmconvert: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,213 @@
+//! Prints information about Netpbm images.
+//!
+//! Usage: `pnminfo [--json] [FILE]...`
+//!
+//! For each file this prints the format, mode, size, max value, comments, and raster size of every
+//! image in the file, followed by the number of images. The raster size is only given for binary
+//! images, as the size of an ASCII raster depends on how the samples were written. Only the headers
+//! and one row are kept in memory, so very large files can be inspected. If no files are given the
+//! images are read from standard input. With `--json` the information is printed as a JSON array
+//! with one object for each file.
+
+extern crate netbpm;
+
+use netbpm::{AnyDecoder, Error, ImageHeader, ImageType, Mode};
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::process;
+
+const USAGE: &str = "Usage: pnminfo [--json] [FILE]...
+
+Prints the format, mode, size, max value, comments, and raster size of every
+image in each FILE. The raster size is only given for binary images. Reads
+from standard input if no FILE is given.
+
+Options:
+    --json    print the information as JSON
+    --help    print this message";
+
+/// The longest row that will be read, in bytes. Only one row is held at a time, so this limits
+/// the memory used however large the images are. Images with longer rows are reported as errors.
+const MAX_ROW_LEN: usize = 1 << 28;
+
+/// The information found in one file.
+struct FileInfo {
+    name: String,
+    headers: Vec<ImageHeader>,
+    error: Option<Error>,
+}
+
+/// Reads the header of every image in a stream.
+///
+/// The raster of each image is read a row at a time and thrown away so the next header can be
+/// found. Reading stops at the first error.
+fn read_info<R: Read>(name: &str, reader: R) -> FileInfo {
+    let mut decoder = AnyDecoder::from_reader(reader);
+    let mut headers:Vec<ImageHeader> = vec![];
+    let error = loop {
+        let mut rows = match decoder.rows() {
+            Ok(rows) => rows,
+            Err(Error::NoMoreImages) => break None,
+            Err(e) => break Some(e),
+        };
+        if rows.row_len() > MAX_ROW_LEN {
+            headers.push(rows.header().clone());
+            break Some(Error::LimitExceeded{limit: "row size", value: rows.row_len() as u64, max: MAX_ROW_LEN as u64});
+        }
+        let mut row = vec![0; rows.row_len()];
+        let result = loop {
+            match rows.read_row(&mut row) {
+                Ok(true) => {},
+                Ok(false) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        headers.push(rows.header().clone());
+        if let Err(e) = result {
+            break Some(e);
+        }
+    };
+    FileInfo{name: name.to_string(), headers, error}
+}
+
+fn format_name(header: &ImageHeader) -> &'static str {
+    match header.image_type {
+        ImageType::PBM => "pbm",
+        ImageType::PGM => "pgm",
+        ImageType::PPM => "ppm",
+        ImageType::PAM => "pam",
+    }
+}
+
+fn magic_number(header: &ImageHeader) -> &'static str {
+    match (header.image_type, header.mode) {
+        (ImageType::PBM, Mode::ASCII) => "P1",
+        (ImageType::PGM, Mode::ASCII) => "P2",
+        (ImageType::PPM, Mode::ASCII) => "P3",
+        (ImageType::PBM, Mode::BINARY) => "P4",
+        (ImageType::PGM, Mode::BINARY) => "P5",
+        (ImageType::PPM, Mode::BINARY) => "P6",
+        (ImageType::PAM, _) => "P7",
+    }
+}
+
+fn mode_name(header: &ImageHeader) -> &'static str {
+    match header.mode {
+        Mode::ASCII => "plain",
+        Mode::BINARY => "raw",
+    }
+}
+
+/// Prints the information for a file as text.
+fn print_text(info: &FileInfo) {
+    println!("{}: {} image{}", info.name, info.headers.len(), if info.headers.len() == 1 { "" } else { "s" });
+    for (i, header) in info.headers.iter().enumerate() {
+        print!("  image {}: {} {} ({}), {}x{}, max value {}, {} channel{}", i + 1, format_name(header),
+               mode_name(header), magic_number(header), header.width, header.height, header.max_val,
+               header.channels, if header.channels == 1 { "" } else { "s" });
+        if !header.tuple_type.is_empty() {
+            print!(", tuple type {}", header.tuple_type);
+        }
+        match header.mode {
+            Mode::BINARY => println!(", raster {} bytes", header.raster_len()),
+            Mode::ASCII => println!(),
+        }
+        for comment in &header.comments {
+            println!("    # {}", comment.text);
+        }
+    }
+    if let Some(ref e) = info.error {
+        println!("  error: {}", e);
+    }
+}
+
+/// Escapes a string for use in JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The size of a binary raster as a JSON value, or `null` for ASCII rasters.
+fn raster_bytes(header: &ImageHeader) -> String {
+    match header.mode {
+        Mode::BINARY => header.raster_len().to_string(),
+        Mode::ASCII => "null".to_string(),
+    }
+}
+
+/// Formats the information for a file as a JSON object.
+fn json_info(info: &FileInfo) -> String {
+    let images:Vec<String> = info.headers.iter().map(|header| {
+        let comments:Vec<String> = header.comments.iter().map(|c| json_string(&c.text)).collect();
+        format!("{{\"format\":\"{}\",\"magic\":\"{}\",\"mode\":\"{}\",\"width\":{},\"height\":{},\"max_val\":{},\
+                 \"channels\":{},\"tuple_type\":{},\"comments\":[{}],\"raster_bytes\":{}}}",
+                format_name(header), magic_number(header), mode_name(header), header.width, header.height,
+                header.max_val, header.channels, json_string(&header.tuple_type), comments.join(","),
+                raster_bytes(header))
+    }).collect();
+    let error = match info.error {
+        Some(ref e) => json_string(&e.to_string()),
+        None => "null".to_string(),
+    };
+    format!("{{\"file\":{},\"count\":{},\"images\":[{}],\"error\":{}}}", json_string(&info.name),
+            info.headers.len(), images.join(","), error)
+}
+
+fn main() {
+    let mut json = false;
+    let mut files:Vec<String> = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if arg.starts_with("--") => {
+                eprintln!("pnminfo: unknown option {}\n\n{}", arg, USAGE);
+                process::exit(2);
+            },
+            _ => files.push(arg),
+        }
+    }
+
+    let mut infos:Vec<FileInfo> = vec![];
+    if files.is_empty() {
+        let stdin = io::stdin();
+        infos.push(read_info("-", stdin.lock()));
+    }
+    for name in &files {
+        let info = match File::open(name) {
+            Ok(file) => read_info(name, file),
+            Err(e) => FileInfo{name: name.clone(), headers: vec![], error: Some(Error::Io(e))},
+        };
+        infos.push(info);
+    }
+
+    if json {
+        let objects:Vec<String> = infos.iter().map(json_info).collect();
+        println!("[{}]", objects.join(","));
+    } else {
+        for info in &infos {
+            print_text(info);
+        }
+    }
+
+    if infos.iter().any(|i| i.error.is_some()) {
+        process::exit(1);
+    }
+}
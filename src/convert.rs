@@ -0,0 +1,174 @@
+//! Provides conversions between pbm, pgm, and ppm images.
+//!
+//! The conversions are methods on `AnyImage`, which records the format of the image along with
+//! its data. Each returns a new image and leaves the original unchanged.
+//!
+//! Take care with the meaning of samples when converting by hand: in a pbm image 1 is black and 0
+//! is white, while in pgm and ppm images 0 is black and the max value is white. The methods here
+//! handle this so the brightness of the image is kept.
+//!
+//! # Examples
+//!
+//! ```
+//! use netbpm::convert::Luminance;
+//!
+//! let data:&[u8] = b"P3\n2 1\n255\n255 255 255  0 0 255\n";
+//! let image = netbpm::decode(data).unwrap();
+//! let gray = image.to_pgm(Luminance::REC601).unwrap();
+//! assert_eq!(gray.dat, vec![255, 29]);
+//! let bitmap = image.to_pbm(Luminance::REC601, 0.5).unwrap();
+//! assert_eq!(bitmap.dat, vec![0, 1]);
+//! ```
+
+use tools::{depth_for, samples_to_u16};
+use AnyImage;
+use BitDepth;
+use Error;
+use ImageType;
+
+/// The weights used to find the brightness of a color.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Luminance {
+    /// The weights from ITU-R BT.601, used by the Netpbm tools: 0.299, 0.587, and 0.114.
+    REC601,
+    /// The weights from ITU-R BT.709, used for HD video and sRGB: 0.2126, 0.7152, and 0.0722.
+    REC709,
+}
+
+impl Luminance {
+    /// Finds the brightness of a color from its red, green, and blue samples.
+    fn apply(self, r: u16, g: u16, b: u16) -> u16 {
+        let (wr, wg, wb) = match self {
+            Luminance::REC601 => (0.299, 0.587, 0.114),
+            Luminance::REC709 => (0.2126, 0.7152, 0.0722),
+        };
+        (wr * r as f64 + wg * g as f64 + wb * b as f64).round() as u16
+    }
+}
+
+impl AnyImage {
+    /// Converts the image to a pgm image.
+    ///
+    /// ppm images are converted using the luminance of each pixel and keep their max value. pbm
+    /// images become black and white pgm images with a max value of 255, where black pixels
+    /// become 0 and white pixels become 255. pgm images are copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if the image is a pam image.
+    pub fn to_pgm(&self, luminance: Luminance) -> Result<AnyImage, Error> {
+        match self.image_type {
+            ImageType::PBM => Ok(self.bits_to_image(ImageType::PGM, 1)),
+            ImageType::PGM => Ok(self.with_samples(ImageType::PGM, self.samples(), self.max_val)),
+            ImageType::PPM => {
+                let gray = self.samples().chunks(3).map(|p| luminance.apply(p[0], p[1], p[2])).collect();
+                Ok(self.with_samples(ImageType::PGM, gray, self.max_val))
+            },
+            ImageType::PAM => Result::Err(Error::InvalidParameter("pam images can not be converted.")),
+        }
+    }
+
+    /// Converts the image to a pbm image.
+    ///
+    /// A pixel becomes black if its brightness is less than `threshold` times the max value, so a
+    /// `threshold` of 0.5 makes everything darker than middle gray black. The brightness of ppm
+    /// pixels is found with `luminance`. pbm images are copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if the image is a pam image or `threshold` isn't between 0 and 1.
+    pub fn to_pbm(&self, luminance: Luminance, threshold: f64) -> Result<AnyImage, Error> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Result::Err(Error::InvalidParameter("The threshold must be between 0 and 1."));
+        }
+        if self.image_type == ImageType::PBM {
+            return Ok(self.with_samples(ImageType::PBM, self.samples(), 1));
+        }
+        let gray = self.to_pgm(luminance)?;
+        let limit = threshold * gray.max_val as f64;
+        let bits = gray.samples().iter().map(|&v| if (v as f64) < limit { 1 } else { 0 }).collect();
+        Ok(self.with_samples(ImageType::PBM, bits, 1))
+    }
+
+    /// Converts the image to a ppm image.
+    ///
+    /// Each pgm sample is copied to the red, green, and blue samples of the pixel. pbm images
+    /// become black and white ppm images with a max value of 255. ppm images are copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if the image is a pam image.
+    pub fn to_ppm(&self) -> Result<AnyImage, Error> {
+        match self.image_type {
+            ImageType::PBM => Ok(self.bits_to_image(ImageType::PPM, 3)),
+            ImageType::PGM => {
+                let color = self.samples().iter().flat_map(|&v| vec![v; 3]).collect();
+                Ok(self.with_samples(ImageType::PPM, color, self.max_val))
+            },
+            ImageType::PPM => Ok(self.with_samples(ImageType::PPM, self.samples(), self.max_val)),
+            ImageType::PAM => Result::Err(Error::InvalidParameter("pam images can not be converted.")),
+        }
+    }
+
+    /// Scales every sample so that the image uses a new max value.
+    ///
+    /// Samples are rounded to the nearest value. The bit depth of the returned image is chosen
+    /// to fit `max_val`, so going from a max value of 255 to 65535 gives a sixteen-bit image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let data:&[u8] = b"P2\n3 1\n15\n0 7 15\n";
+    /// let image = netbpm::decode(data).unwrap().with_max_val(255).unwrap();
+    /// assert_eq!(image.dat, vec![0, 119, 255]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if the image is a pbm image, whose max value is always 1, or if
+    /// `max_val` is 0.
+    pub fn with_max_val(&self, max_val: u16) -> Result<AnyImage, Error> {
+        if self.image_type == ImageType::PBM {
+            return Result::Err(Error::InvalidParameter("The max value of a pbm image can not be changed."));
+        }
+        if max_val == 0 {
+            return Result::Err(Error::InvalidParameter("The max value must be at least 1."));
+        }
+        let old = self.max_val.max(1) as u32;
+        let new = max_val as u32;
+        let samples = self.samples().iter().map(|&v| ((v as u32).min(old) * new + old / 2) / old)
+            .map(|v| v as u16).collect();
+        let mut image = self.with_samples(self.image_type, samples, max_val);
+        image.channels = self.channels;
+        image.tuple_type = self.tuple_type.clone();
+        Ok(image)
+    }
+
+    /// Gets the samples of the image as `u16`s.
+    fn samples(&self) -> Vec<u16> {
+        samples_to_u16(&self.dat, &self.depth)
+    }
+
+    /// Converts a pbm image to black and white samples with a max value of 255.
+    fn bits_to_image(&self, image_type: ImageType, channels: usize) -> AnyImage {
+        let samples = self.dat.iter().flat_map(|&bit| vec![if bit == 0 { 255 } else { 0 }; channels]).collect();
+        self.with_samples(image_type, samples, 255)
+    }
+
+    /// Makes a new image with the same size, mode, and comments as this one.
+    fn with_samples(&self, image_type: ImageType, samples: Vec<u16>, max_val: u16) -> AnyImage {
+        let channels = if image_type == ImageType::PPM { 3 } else { 1 };
+        let depth = if image_type == ImageType::PBM { BitDepth::EIGHT } else { depth_for(max_val) };
+        let mut dat:Vec<u8> = Vec::with_capacity(samples.len() * 2);
+        for v in samples {
+            // Samples in a damaged file can be greater than its max value.
+            let v = v.min(max_val);
+            if depth == BitDepth::SIXTEEN {
+                dat.push((v >> 8) as u8);
+            }
+            dat.push(v as u8);
+        }
+        AnyImage{image_type, mode: self.mode, width: self.width, height: self.height, channels, dat, depth,
+                 max_val, tuple_type: String::new(), comments: self.comments.clone()}
+    }
+}
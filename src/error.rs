@@ -0,0 +1,139 @@
+//! The error type returned by the encoders and decoders.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The errors that can happen while saving or loading an image.
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the underlying file, reader, or writer.
+    Io(io::Error),
+    /// The data doesn't start with a magic number the decoder can read.
+    ///
+    /// `expected` is the format the decoder reads, such as `"pgm"`. It is `None` if the data isn't
+    /// a Netpbm file at all.
+    BadMagicNumber { expected: Option<&'static str> },
+    /// A character in the header that isn't allowed there.
+    UnexpectedCharacter { character: u8, location: Location },
+    /// The header is malformed in some way other than an unexpected character.
+    InvalidHeader(String),
+    /// The data ended before the end of the image.
+    Truncated,
+    /// A character in an ASCII raster other than a digit, whitespace, or comment.
+    ///
+    /// `offset` is the number of bytes from the start of the raster to the character.
+    UnexpectedRasterCharacter { character: u8, offset: usize },
+    /// A sample in the raster that is greater than the max value of the image.
+    SampleOutOfRange { value: u32, max_val: u16 },
+    /// The amount of image data doesn't match the width, height, and number of channels.
+    DimensionMismatch { expected: usize, found: usize },
+    /// A different number of rows was written than the height of the image.
+    RowCount { expected: u32, found: u32 },
+    /// A max value outside of the range 1 to 65535.
+    UnsupportedMaxVal(u32),
+    /// A decoder was asked for an image after the last one in the stream.
+    NoMoreImages,
+    /// An encoder was given options it can't save.
+    InvalidParameter(&'static str),
+    /// The image is larger than one of the `Limits` set on the decoder.
+    ///
+    /// `limit` names the limit, such as `"width"`, `value` is the size of the image, and `max` is
+    /// the limit it went over.
+    LimitExceeded { limit: &'static str, value: u64, max: u64 },
+}
+
+/// Where in a header an error was found.
+///
+/// The offset is counted in bytes from the start of the image, so for the second image in a file
+/// it is counted from the magic number of that image. Lines and columns start at 1, and only a LF
+/// starts a new line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {} (byte {})", self.line, self.column, self.offset)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::BadMagicNumber { expected: None } => write!(f, "Input file is not a netbpm file."),
+            Error::BadMagicNumber { expected: Some(format) } => write!(f, "Input file is not a {} file.", format),
+            Error::UnexpectedCharacter { character, location } => write!(f, "Unexpected character in file header at {}. Character: {}", location, character),
+            Error::InvalidHeader(ref msg) => write!(f, "{}", msg),
+            Error::Truncated => write!(f, "File ended before the end of the image."),
+            Error::UnexpectedRasterCharacter { character, offset } => write!(f, "Unexpected character in image data at byte {} of the raster. Character: {}", offset, character),
+            Error::SampleOutOfRange { value, max_val } => write!(f, "Sample {} is greater than the max value of {}.", value, max_val),
+            Error::DimensionMismatch { expected, found } => write!(f, "Expected {} bytes of image data but found {}.", expected, found),
+            Error::RowCount { expected, found } => write!(f, "Expected {} rows but found {}.", expected, found),
+            Error::UnsupportedMaxVal(max_val) => write!(f, "Unsupported max value: {}", max_val),
+            Error::NoMoreImages => write!(f, "No more images in file."),
+            Error::InvalidParameter(msg) => write!(f, "{}", msg),
+            Error::LimitExceeded { limit, value, max } => write!(f, "Image {} of {} is over the limit of {}.", limit, value, max),
+        }
+    }
+}
+
+/// A problem that was worked around while loading an image with `load_lenient`.
+///
+/// Each kind of problem is reported once, with a count of how often it was found.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Warning {
+    /// Samples greater than the max value, which were lowered to the max value.
+    SampleAboveMaxVal { count: usize },
+    /// Characters in an ASCII raster other than digits, whitespace, and comments, which were
+    /// skipped. `offset` is the number of bytes from the start of the raster to the first one.
+    UnexpectedCharacters { count: usize, offset: usize },
+    /// The data ended before the end of the raster. The missing samples were set to 0 and `rows`
+    /// rows, from the top, were read in full.
+    Truncated { rows: u32 },
+    /// Data after the raster that isn't another image, which was skipped. `bytes` doesn't count
+    /// whitespace before the data.
+    TrailingData { bytes: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::SampleAboveMaxVal { count } => write!(f, "{} samples were greater than the max value.", count),
+            Warning::UnexpectedCharacters { count, offset } => write!(f, "{} unexpected characters in image data, the first at byte {} of the raster.", count, offset),
+            Warning::Truncated { rows } => write!(f, "File ended before the end of the image after {} rows.", rows),
+            Warning::TrailingData { bytes } => write!(f, "{} bytes of data after the end of the image.", bytes),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    /// Converts back to an `io::Error` so the encoders and decoders can be used in functions that
+    /// return `io::Result`. I/O errors are returned as they were and everything else becomes an
+    /// `InvalidData` error wrapping the original.
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
@@ -1,7 +1,12 @@
+#![allow(clippy::assertions_on_constants)]
+
 use BitDepth;
+use Comment;
+use Error;
 use Mode;
-use tools::{get_header,ImageType};
-use std::error::Error;
+use tools::get_header;
+use ImageType;
+use Location;
 
 #[test]
 fn invalid_magic_number() {
@@ -9,7 +14,10 @@ fn invalid_magic_number() {
     let header = get_header(&dat);
     match header {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Input file is not a netbpm file."),
+        Err(e) => match e {
+            Error::BadMagicNumber{expected: None} => {},
+            _ => assert!(false),
+        },
     }
 }
 
@@ -19,7 +27,10 @@ fn invalid_magic_number_two() {
     let header = get_header(&dat);
     match header {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Input file is not a netbpm file."),
+        Err(e) => match e {
+            Error::BadMagicNumber{expected: None} => {},
+            _ => assert!(false),
+        },
     }
 }
 
@@ -29,7 +40,13 @@ fn read_header_bad_char() {
     let header = get_header(&dat);
     match header {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Unexpected character in file header. Character: 63"),
+        Err(e) => match e {
+            Error::UnexpectedCharacter{character, location} => {
+                assert_eq!(character, 63);
+                assert_eq!(location, Location{offset: 4, line: 2, column: 2});
+            },
+            _ => assert!(false),
+        },
     }
 }
 
@@ -150,6 +167,7 @@ fn read_pgm_header_eight_sub_255() {
     assert_eq!(header.dat_start, 10);
     assert_eq!(header.image_type, ImageType::PGM);
     assert_eq!(header.depth, BitDepth::EIGHT);
+    assert_eq!(header.max_val, 1);
     assert_eq!(header.mode, Mode::BINARY);
 }
 
@@ -162,6 +180,7 @@ fn read_pgm_header_sixteen_sub_65535() {
     assert_eq!(header.dat_start, 12);
     assert_eq!(header.image_type, ImageType::PGM);
     assert_eq!(header.depth, BitDepth::SIXTEEN);
+    assert_eq!(header.max_val, 256);
     assert_eq!(header.mode, Mode::ASCII);
 }
 
@@ -236,3 +255,277 @@ fn read_ppm_header_ascii_eight_commented() {
     assert_eq!(header.depth, BitDepth::EIGHT);
     assert_eq!(header.mode, Mode::ASCII);
 }
+
+#[test]
+fn read_pbm_header_max_val() {
+    let dat:Vec<u8> = vec![b'P', b'4', 10, b'6', b' ', b'1', b'0', 10];
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.max_val, 1);
+}
+
+#[test]
+fn read_pgm_header_twelve_bit() {
+    let dat:Vec<u8> = b"P5\n6 10\n4095\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.dat_start, 13);
+    assert_eq!(header.depth, BitDepth::SIXTEEN);
+    assert_eq!(header.max_val, 4095);
+}
+
+#[test]
+fn read_pgm_header_zero_max_val() {
+    let dat:Vec<u8> = b"P5\n6 10\n0\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::UnsupportedMaxVal(max_val) => assert_eq!(max_val, 0),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn read_ppm_header_max_val_too_large() {
+    let dat:Vec<u8> = b"P6\n6 10\n65536\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::UnsupportedMaxVal(max_val) => assert_eq!(max_val, 65536),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn read_pam_header() {
+    let dat:Vec<u8> = b"P7\nWIDTH 6\nHEIGHT 10\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 6);
+    assert_eq!(header.height, 10);
+    assert_eq!(header.channels, 4);
+    assert_eq!(header.dat_start, 66);
+    assert_eq!(header.image_type, ImageType::PAM);
+    assert_eq!(header.depth, BitDepth::EIGHT);
+    assert_eq!(header.mode, Mode::BINARY);
+    assert_eq!(header.tuple_type, "RGB_ALPHA");
+}
+
+#[test]
+fn read_pam_header_sixteen_commented() {
+    let dat:Vec<u8> = b"P7\n# Comment\nHEIGHT 10\nWIDTH 6\n\nDEPTH 2\nMAXVAL 65535\nENDHDR\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 6);
+    assert_eq!(header.height, 10);
+    assert_eq!(header.channels, 2);
+    assert_eq!(header.dat_start, 60);
+    assert_eq!(header.image_type, ImageType::PAM);
+    assert_eq!(header.depth, BitDepth::SIXTEEN);
+    assert_eq!(header.max_val, 65535);
+    assert_eq!(header.tuple_type, "");
+}
+
+#[test]
+fn read_pam_header_multiple_tuple_types() {
+    let dat:Vec<u8> = b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 1\nTUPLTYPE BLACKANDWHITE\nTUPLTYPE EXTRA\nENDHDR\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.tuple_type, "BLACKANDWHITE EXTRA");
+}
+
+#[test]
+fn read_pam_header_missing_field() {
+    let dat:Vec<u8> = b"P7\nWIDTH 6\nHEIGHT 10\nMAXVAL 255\nENDHDR\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"Pam header is missing WIDTH, HEIGHT, DEPTH, or MAXVAL."),
+    }
+}
+
+#[test]
+fn read_pam_header_no_end() {
+    let dat:Vec<u8> = b"P7\nWIDTH 6\nHEIGHT 10\nDEPTH 1\nMAXVAL 255\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::Truncated => {},
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn read_pam_header_bad_value() {
+    let dat:Vec<u8> = b"P7\nWIDTH six\nHEIGHT 10\nDEPTH 1\nMAXVAL 255\nENDHDR\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"Invalid value for WIDTH in pam header at line 2, column 1 (byte 3): six"),
+    }
+}
+
+#[test]
+fn read_pam_header_zero_depth() {
+    let dat:Vec<u8> = b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 0\nMAXVAL 255\nENDHDR\n".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"Pam header at line 4, column 1 (byte 20) has a DEPTH of 0."),
+    }
+}
+
+#[test]
+fn read_header_empty() {
+    let dat:Vec<u8> = vec![];
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::Truncated => {},
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn read_header_truncated() {
+    let dat:Vec<u8> = b"P5\n6 10\n25".to_vec();
+    let header = get_header(&dat);
+    match header {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::Truncated => {},
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn read_header_comments() {
+    let dat:Vec<u8> = b"P2 # camera 2\n3\n#exposure 1/60\r\n2 # pipeline v4\n255\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 3);
+    assert_eq!(header.height, 2);
+    assert_eq!(header.max_val, 255);
+    assert_eq!(header.dat_start, dat.len());
+    assert_eq!(header.comments.len(), 3);
+    assert_eq!(header.comments[0], Comment{text: "camera 2".to_string(), position: 0});
+    assert_eq!(header.comments[1], Comment{text: "exposure 1/60".to_string(), position: 1});
+    assert_eq!(header.comments[2], Comment{text: "pipeline v4".to_string(), position: 2});
+}
+
+#[test]
+fn read_pam_header_comments() {
+    let dat:Vec<u8> = b"P7\n# first\nWIDTH 6\nHEIGHT 10\n  # third\nDEPTH 2\nMAXVAL 255\nENDHDR\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.comments.len(), 2);
+    assert_eq!(header.comments[0], Comment{text: "first".to_string(), position: 0});
+    assert_eq!(header.comments[1], Comment{text: "third".to_string(), position: 2});
+}
+
+#[test]
+fn read_header_overflow() {
+    let dat:Vec<u8> = b"P5\n99999999999 1\n255\n".to_vec();
+    match get_header(&dat) {
+        Err(Error::InvalidHeader(_)) => {},
+        _ => assert!(false),
+    }
+    let dat:Vec<u8> = b"P5\n1 4294967296\n255\n".to_vec();
+    match get_header(&dat) {
+        Err(Error::InvalidHeader(_)) => {},
+        _ => assert!(false),
+    }
+    let dat:Vec<u8> = b"P5\n4294967295 1\n255\n".to_vec();
+    assert_eq!(get_header(&dat).unwrap().width, 4294967295);
+}
+
+#[test]
+fn read_header_max_val_overflow() {
+    let dat:Vec<u8> = b"P5\n1 1\n99999999999999\n".to_vec();
+    match get_header(&dat) {
+        Err(Error::UnsupportedMaxVal(_)) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn read_header_whitespace_runs() {
+    let dat:Vec<u8> = b"P5  \t6   10\r\n\r\n255\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 6);
+    assert_eq!(header.height, 10);
+    assert_eq!(header.max_val, 255);
+    assert_eq!(header.dat_start, dat.len());
+}
+
+#[test]
+fn read_header_crlf() {
+    // Only the CR after the max value is part of the header.
+    let dat:Vec<u8> = b"P5\r\n6 10\r\n255\r\n".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 6);
+    assert_eq!(header.height, 10);
+    assert_eq!(header.dat_start, dat.len() - 1);
+}
+
+#[test]
+fn read_header_comment_after_value() {
+    let dat:Vec<u8> = b"P2#a\n6#b\n10#c\r255#d\nraster".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.width, 6);
+    assert_eq!(header.height, 10);
+    assert_eq!(header.max_val, 255);
+    assert_eq!(header.dat_start, dat.len() - 6);
+    let positions:Vec<u32> = header.comments.iter().map(|c| c.position).collect();
+    assert_eq!(positions, vec![0, 1, 2, 3]);
+    assert_eq!(header.comments[3].text, "d");
+}
+
+#[test]
+fn read_header_single_whitespace_after_max_val() {
+    // The raster starts straight after the first whitespace, even if it looks like more header.
+    let dat:Vec<u8> = b"P5 1 2 255\n\n#".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.dat_start, 11);
+    let dat:Vec<u8> = b"P4 8 1\n ".to_vec();
+    let header = get_header(&dat).unwrap();
+    assert_eq!(header.dat_start, 7);
+}
+
+#[test]
+fn read_header_missing_whitespace() {
+    let dat:Vec<u8> = b"P56 10\n255\n".to_vec();
+    match get_header(&dat) {
+        Err(Error::UnexpectedCharacter{character: b'6', location}) => {
+            assert_eq!(location, Location{offset: 2, line: 1, column: 3});
+        },
+        _ => assert!(false),
+    }
+    let dat:Vec<u8> = b"P5\n6 10\n255x".to_vec();
+    match get_header(&dat) {
+        Err(Error::UnexpectedCharacter{character: b'x', location}) => {
+            assert_eq!(location, Location{offset: 11, line: 3, column: 4});
+        },
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn read_header_unfinished_comment() {
+    let dat:Vec<u8> = b"P5\n6 10\n# no end".to_vec();
+    match get_header(&dat) {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn read_header_error_location() {
+    let dat:Vec<u8> = b"P5\n# size\n 99999999999 1\n255\n".to_vec();
+    match get_header(&dat) {
+        Err(e) => assert_eq!(e.to_string(), "Width or height in header is too large at line 3, column 2 (byte 11)."),
+        _ => assert!(false),
+    }
+}
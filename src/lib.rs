@@ -1,26 +1,189 @@
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Mode {
     ASCII,
     BINARY,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BitDepth {
     EIGHT,
     SIXTEEN,
 }
 
+/// The Netpbm format of an image, found from the magic number at the start of the file.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageType {
+    PBM,
+    PGM,
+    PPM,
+    PAM,
+}
+
+/// A comment from the header of an image.
+///
+/// The text doesn't include the `#` or a single space after it, so the header line
+/// `# exposure 1/60` has the text `exposure 1/60`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Comment {
+    pub text: String,
+    /// The number of header values before the comment, not counting the magic number. In the
+    /// header `P2 #a` followed by `3 2 #b` on the next line, `a` is at position 0 and `b` at
+    /// position 2. In pam headers each line such as `WIDTH 3` counts as one value.
+    pub position: u32,
+}
+
+/// Limits on the size of images a decoder will load.
+///
+/// The limits are checked as soon as the header has been read, before any memory is allocated
+/// for the raster, so a header claiming a huge image can't make the decoder run out of memory.
+/// Images over a limit are rejected with `Error::LimitExceeded`. The default has no limits other
+/// than what can be allocated at all. Set the limits with `set_limits` on a decoder.
+///
+/// # Examples
+///
+/// ```
+/// use netbpm::pgm::PGMDecoder;
+/// use netbpm::{Error,Limits};
+///
+/// let data:&[u8] = b"P5\n100000 100000\n255\n";
+/// let mut decoder = PGMDecoder::from_reader(data);
+/// decoder.set_limits(Limits{max_pixels: 50_000_000, ..Limits::default()});
+/// match decoder.load() {
+///     Err(Error::LimitExceeded{limit, ..}) => assert_eq!(limit, "pixel count"),
+///     _ => panic!(),
+/// }
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Limits {
+    /// The largest width allowed, in pixels.
+    pub max_width: u32,
+    /// The largest height allowed, in pixels.
+    pub max_height: u32,
+    /// The largest number of pixels allowed, which is the width times the height.
+    pub max_pixels: u64,
+    /// The largest number of bytes that will be allocated for the header or the image data.
+    pub max_alloc: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits{max_width: u32::MAX, max_height: u32::MAX, max_pixels: u64::MAX, max_alloc: u64::MAX}
+    }
+}
+
 pub struct Image {
     pub width:u32,
     pub height:u32,
     pub dat: Vec<u8>,
     pub depth: BitDepth,
+    /// The largest value a sample can have. For pbm images this is always 1.
+    pub max_val: u16,
+    /// The comments from the header of the file the image was loaded from.
+    pub comments: Vec<Comment>,
+}
+
+/// An image that borrows its data instead of owning it.
+///
+/// Views are made by `pgm::view` and `ppm::view`, which point `dat` at the raster inside a buffer
+/// holding the whole file, so no samples are copied. The fields are the same as those of `Image`.
+///
+/// # Examples
+///
+/// ```
+/// let file:&[u8] = b"P5\n2 1\n255\n\x10\x20";
+/// let view = netbpm::pgm::view(file).unwrap();
+/// assert_eq!(view.dat, &file[11..]);
+/// let image = view.to_image();
+/// assert_eq!(image.dat, vec![0x10, 0x20]);
+/// ```
+pub struct ImageView<'a> {
+    pub width:u32,
+    pub height:u32,
+    pub dat: &'a [u8],
+    pub depth: BitDepth,
+    /// The largest value a sample can have.
+    pub max_val: u16,
+    /// The comments from the header of the file.
+    pub comments: Vec<Comment>,
+}
+
+impl<'a> ImageView<'a> {
+    /// Copies the view into an `Image` that owns its data.
+    pub fn to_image(&self) -> Image {
+        Image{width: self.width, height: self.height, dat: self.dat.to_vec(), depth: self.depth, max_val: self.max_val,
+              comments: self.comments.clone()}
+    }
+}
+
+impl Image {
+    /// Borrows the image as an `ImageView`.
+    ///
+    /// This lets code written for views be used with loaded images as well.
+    pub fn view(&self) -> ImageView<'_> {
+        ImageView{width: self.width, height: self.height, dat: &self.dat, depth: self.depth, max_val: self.max_val,
+                  comments: self.comments.clone()}
+    }
+}
+
+/// An image loaded by `load_partial`, which may have been cut short.
+///
+/// If the data ended before the end of the raster the missing samples are set to 0 and
+/// `complete` is false. `rows` tells how much of the image can be trusted.
+pub struct PartialImage<T> {
+    pub image: T,
+    /// The number of rows, from the top, that were read in full.
+    pub rows: u32,
+    /// Whether the whole raster was read.
+    pub complete: bool,
+}
+
+/// An image loaded by `load_lenient`, along with the problems that were found in it.
+///
+/// The image is as close to what the file meant as the decoder could make it. An empty list of
+/// warnings means the file was valid and the image is the same as one loaded by `load`.
+pub struct LenientImage<T> {
+    pub image: T,
+    pub warnings: Vec<Warning>,
 }
 
+/// An image with each sample stored as a `u16`.
+///
+/// This holds the same image as `Image` but the samples don't need to be put together from pairs
+/// of bytes. Samples from eight-bit images are widened without being scaled, so `max_val` still
+/// gives the range of the samples.
+pub struct Image16 {
+    pub width:u32,
+    pub height:u32,
+    pub dat: Vec<u16>,
+    /// The largest value a sample can have.
+    pub max_val: u16,
+    /// The comments from the header of the file the image was loaded from.
+    pub comments: Vec<Comment>,
+}
+
+impl From<Image> for Image16 {
+    fn from(image: Image) -> Image16 {
+        let dat = tools::samples_to_u16(&image.dat, &image.depth);
+        Image16{width: image.width, height: image.height, dat, max_val: image.max_val, comments: image.comments}
+    }
+}
+
+mod any;
+mod error;
 mod tools;
 #[cfg(test)]
 mod header_tests;
 
+pub mod convert;
+pub mod pam;
 pub mod pbm;
+pub mod pfm;
 pub mod pgm;
+pub mod pixel;
 pub mod ppm;
+pub mod rows;
+
+pub use any::{decode, open, probe, AnyDecoder, AnyImage};
+pub use error::{Error, Location, Warning};
+pub use tools::ImageHeader;
@@ -0,0 +1,610 @@
+//! Provides features for saving and loading images in the Portable Arbitrary Map format.
+//!
+//! The pam (Portable Arbitrary Map) format is a generalization of the other formats. Each pixel
+//! is a tuple of any number of samples (the "depth" of the image) and the header carries an
+//! optional tuple type, such as `RGB_ALPHA` or `GRAYSCALE_ALPHA`, describing what the samples
+//! mean. Samples can be either 8 bits or 16 bits. Unlike the other formats there is no ascii
+//! version of pam, so all images are saved in binary.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::fs;
+//! use netbpm::pam::{PAMEncoder,PAMDecoder};
+//! use netbpm::BitDepth;
+//!
+//! // A 2x1 image of one opaque red pixel and one transparent green pixel.
+//! let dat:[u8;8] = [255,0,0,255, 0,255,0,0];
+//! let mut encoder = PAMEncoder::new("test_file.pam").unwrap();
+//! encoder.save(&dat, 2, 1, 4, BitDepth::EIGHT, "RGB_ALPHA").unwrap();
+//!
+//! let mut decoder = PAMDecoder::new("test_file.pam").unwrap();
+//! let image = decoder.load().unwrap();
+//! assert_eq!(image.channels, 4);
+//! assert_eq!(image.tuple_type, "RGB_ALPHA");
+//! # let _ = fs::remove_file("test_file.pam");
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
+use tools::{check_buffer, check_len, check_stride, depth_for, encoder_row_len, full_max_val, load_raster, load_raster_into, load_raster_partial, rescale, ImageHeader, Stream};
+use BitDepth;
+use Comment;
+use rows::{write_image, RowWriter, Rows};
+use Error;
+use ImageType;
+use Limits;
+use Mode;
+use PartialImage;
+
+/// An image loaded from a pam file.
+///
+/// The data is stored row by row with the samples of each tuple next to each other. 16-bit
+/// samples are stored as two bytes with the most significant byte first.
+pub struct PAMImage {
+    pub width:u32,
+    pub height:u32,
+    /// The number of samples in each tuple. This is called `DEPTH` in the pam header.
+    pub channels:u32,
+    pub dat: Vec<u8>,
+    pub depth: BitDepth,
+    /// The largest value a sample can have. This is called `MAXVAL` in the pam header.
+    pub max_val: u16,
+    /// The value of the `TUPLTYPE` header field. Empty if the file doesn't have one.
+    pub tuple_type: String,
+    /// The comments from the header of the file the image was loaded from.
+    pub comments: Vec<Comment>,
+}
+
+/// Encodes an image as a pam file.
+///
+/// The encoder can write to anything that implements `Write`, such as a file, a socket, or a
+/// `Vec<u8>` in memory. `new` and `append` are provided as shortcuts for writing to files.
+pub struct PAMEncoder<W: Write = File> {
+    f: W,
+    comments: Vec<Comment>,
+}
+
+/// Decodes an image in the pam format.
+///
+/// The decoder can read from anything that implements `Read`, such as a file, a pipe, or a byte
+/// slice in memory. `new` is provided as a shortcut for reading from files.
+pub struct PAMDecoder<R: Read = File> {
+    stream: Stream<R>,
+}
+
+impl PAMEncoder<File> {
+    /// Create a new `PAMEncoder`
+    ///
+    /// Creates a new `PAMEncoder` by creating a file with the specified name. The file extension
+    /// is not important for using this module to encode/decode pam images.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  # use std::fs;
+    /// use netbpm::pam::PAMEncoder;
+    ///
+    /// let encoder = PAMEncoder::new("pam_file.pam").unwrap();
+    /// # let _ = fs::remove_file("pam_file.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be created.
+    pub fn new(file_name: &str) -> Result<PAMEncoder, Error> {
+        let file = File::create(file_name)?;
+        Ok(PAMEncoder{f : file, comments: vec![]})
+    }
+
+    /// Create a `PAMEncoder` that adds images to the end of a file.
+    ///
+    /// The standard allows several images to be stored one after another in the same file. Each
+    /// call to `save` writes a complete image, so an encoder made with this method can be used to
+    /// add frames to an existing stream. The file is created if it doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::pam::{PAMEncoder,PAMDecoder};
+    /// use netbpm::BitDepth;
+    ///
+    /// let frame:[u8;4] = [0,255, 255,0];
+    /// let mut encoder = PAMEncoder::new("frames.pam").unwrap();
+    /// encoder.save(&frame, 2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA").unwrap();
+    ///
+    /// let mut encoder = PAMEncoder::append("frames.pam").unwrap();
+    /// encoder.save(&frame, 2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA").unwrap();
+    ///
+    /// let decoder = PAMDecoder::new("frames.pam").unwrap();
+    /// assert_eq!(decoder.count(), 2);
+    /// # let _ = fs::remove_file("frames.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened or created.
+    pub fn append(file_name: &str) -> Result<PAMEncoder, Error> {
+        let file = OpenOptions::new().append(true).create(true).open(file_name)?;
+        Ok(PAMEncoder{f : file, comments: vec![]})
+    }
+}
+
+impl<W: Write> PAMEncoder<W> {
+    /// Create a new `PAMEncoder` that writes to any `Write`.
+    ///
+    /// Each call to `save` writes a complete image, so several images can be written to the same
+    /// stream one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMEncoder;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let encoder = PAMEncoder::from_writer(&mut buffer);
+    /// ```
+    pub fn from_writer(writer: W) -> PAMEncoder<W> {
+        PAMEncoder{f : writer, comments: vec![]}
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.f
+    }
+
+    /// Sets the comments written in the header of every image saved after this.
+    ///
+    /// Each comment is written on its own line at its position in the header. The comments stay
+    /// set until this is called again, so pass an empty slice to stop writing them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMEncoder;
+    /// use netbpm::{BitDepth,Comment};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PAMEncoder::from_writer(&mut buffer);
+    /// encoder.set_comments(&[Comment{text: "camera 2".to_string(), position: 0}]);
+    /// encoder.save(&[0,255], 1, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA").unwrap();
+    /// ```
+    pub fn set_comments(&mut self, comments: &[Comment]) {
+        self.comments = comments.to_vec();
+    }
+
+    /// Builds the header for an image, including the comments set on the encoder.
+    fn header(&self, width: u32, height: u32, channels: u32, max_val: u16, tuple_type: &str) -> ImageHeader {
+        ImageHeader{width, height, dat_start: 0, image_type: ImageType::PAM, depth: depth_for(max_val), max_val,
+                    mode: Mode::BINARY, channels, tuple_type: tuple_type.to_string(), comments: self.comments.clone()}
+    }
+
+    /// Saves image data to the file stored by the `PAMEncoder`.
+    ///
+    /// This method will record image data to the file. It takes a slice with the data as bytes,
+    /// the width, the height, the number of channels in each tuple, the `BitDepth`, and the tuple
+    /// type. 16-bit data must be given as two bytes per sample with the most significant byte
+    /// first. If the tuple type is empty no `TUPLTYPE` line is written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::pam::PAMEncoder;
+    /// use netbpm::BitDepth;
+    ///
+    /// // A 2x2 gray scale image with an alpha channel.
+    /// const data:[u8;8] = [0,255,   255,255,
+    ///                      255,128, 0,0];
+    ///
+    /// let mut encoder = PAMEncoder::new("test_file_ga.pam").unwrap();
+    /// encoder.save(&data, 2, 2, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA").unwrap();
+    /// # let _ = fs::remove_file("test_file_ga.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Aside from the regular errors associated with file access this function will throw an
+    /// error if the number of channels is zero or if the tuple type contains a line break. A
+    /// `DimensionMismatch` error is returned if `dat` doesn't hold exactly one sample for each
+    /// channel of each pixel.
+    pub fn save(&mut self, dat: &[u8], width: u32, height: u32, channels: u32, depth: BitDepth, tuple_type: &str) -> Result<(), Error> {
+        let max_val = full_max_val(&depth);
+        self.save_max_val(dat, width, height, channels, max_val, tuple_type)
+    }
+
+    /// Saves image data with an arbitrary max value.
+    ///
+    /// This works the same as `save` except that the max value written to the header can be any
+    /// value from 1 to 65535 instead of only 255 or 65535. Samples are stored in one byte if the
+    /// max value is less than 256 and in two bytes, most significant byte first, otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::pam::PAMEncoder;
+    ///
+    /// // A 2x1 black and white image with an alpha channel.
+    /// const data:[u8;4] = [0,1, 1,1];
+    ///
+    /// let mut encoder = PAMEncoder::new("test_file_bw.pam").unwrap();
+    /// encoder.save_max_val(&data, 2, 1, 2, 1, "BLACKANDWHITE_ALPHA").unwrap();
+    /// # let _ = fs::remove_file("test_file_bw.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by `save` this will return an error if the max value
+    /// is 0, and `SampleOutOfRange` if a sample is greater than the max value. Nothing is written
+    /// if any sample is out of range.
+    pub fn save_max_val(&mut self, dat: &[u8], width: u32, height: u32, channels: u32, max_val: u16, tuple_type: &str) -> Result<(), Error> {
+        check_params(channels, max_val, tuple_type)?;
+        let row_len = encoder_row_len(width, channels, max_val)?;
+        check_len(dat, row_len, height)?;
+        let header = self.header(width, height, channels, max_val, tuple_type);
+        write_image(&mut self.f, header, dat, row_len)
+    }
+
+    /// Saves image data whose rows are spaced out in a larger buffer.
+    ///
+    /// This works the same as `save_max_val` except that each row starts `stride` bytes after the
+    /// start of the row before it, rather than straight after it. Any bytes between the rows are
+    /// skipped. This saves part of a larger image, or an image with padded rows, without copying
+    /// it first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMEncoder;
+    ///
+    /// // Each row of gray scale and alpha pairs is followed by two bytes of padding.
+    /// const data:[u8;8] = [0,255, 9,9,
+    ///                      255,128, 9,9];
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PAMEncoder::from_writer(&mut buffer);
+    /// encoder.save_stride(&data, 1, 2, 4, 2, 255, "GRAYSCALE_ALPHA").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if `stride` is less than the length of a row and
+    /// `DimensionMismatch` if `dat` ends before the end of the last row. Otherwise this returns the
+    /// same errors as `save_max_val`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn save_stride(&mut self, dat: &[u8], width: u32, height: u32, stride: usize, channels: u32, max_val: u16, tuple_type: &str) -> Result<(), Error> {
+        check_params(channels, max_val, tuple_type)?;
+        let row_len = encoder_row_len(width, channels, max_val)?;
+        check_stride(dat, row_len, height, stride)?;
+        let header = self.header(width, height, channels, max_val, tuple_type);
+        write_image(&mut self.f, header, dat, stride)
+    }
+
+    /// Starts saving an image one row at a time.
+    ///
+    /// The header is written straight away from the width, height, number of channels, `BitDepth`,
+    /// and tuple type. Each row is then written with `write_row` on the returned `RowWriter`, so
+    /// the whole image never needs to be in memory at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMEncoder;
+    /// use netbpm::BitDepth;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PAMEncoder::from_writer(&mut buffer);
+    /// let mut writer = encoder.rows(2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA").unwrap();
+    /// writer.write_row(&[0,255, 255,128]).unwrap();
+    /// writer.finish().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `save` for the header.
+    pub fn rows(&mut self, width: u32, height: u32, channels: u32, depth: BitDepth, tuple_type: &str) -> Result<RowWriter<'_, W>, Error> {
+        let max_val = full_max_val(&depth);
+        self.rows_max_val(width, height, channels, max_val, tuple_type)
+    }
+
+    /// Starts saving an image with an arbitrary max value one row at a time.
+    ///
+    /// This works the same as `rows` except that the max value can be anything from 1 to 65535, as
+    /// with `save_max_val`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `save_max_val` for the header.
+    pub fn rows_max_val(&mut self, width: u32, height: u32, channels: u32, max_val: u16, tuple_type: &str) -> Result<RowWriter<'_, W>, Error> {
+        check_params(channels, max_val, tuple_type)?;
+        let header = self.header(width, height, channels, max_val, tuple_type);
+        RowWriter::new(&mut self.f, header)
+    }
+
+}
+
+/// Checks the parts of a pam header that the encoder can't save.
+fn check_params(channels: u32, max_val: u16, tuple_type: &str) -> Result<(), Error> {
+    if max_val == 0 {
+        return Result::Err(Error::UnsupportedMaxVal(0));
+    }
+    if channels == 0 {
+        return Result::Err(Error::InvalidParameter("Pam images must have at least one channel."));
+    }
+    if tuple_type.contains('\n') || tuple_type.contains('\r') {
+        return Result::Err(Error::InvalidParameter("Pam tuple type can not contain a line break."));
+    }
+    Ok(())
+}
+
+impl PAMDecoder<File> {
+    /// Create a new `PAMDecoder`
+    ///
+    /// Creates a new `PAMDecoder` that reads from the specified file. The file extension does not
+    /// matter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::fs::File;
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// # let _ = File::create("saved_file.pam");
+    /// let decoder = PAMDecoder::new("saved_file.pam").unwrap();
+    ///  # let _ = fs::remove_file("saved_file.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened.
+    pub fn new(file_name: &str) -> Result<PAMDecoder, Error> {
+        let file = File::open(file_name)?;
+        Ok(PAMDecoder{stream : Stream::new(file)})
+    }
+}
+
+impl<R: Read> PAMDecoder<R> {
+    /// Create a new `PAMDecoder` that reads from any `Read`.
+    ///
+    /// Only as much data as is needed is read for each image, so images can be loaded from a pipe
+    /// or socket as they arrive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// let data:&[u8] = b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nENDHDR\n\x00\xff";
+    /// let mut decoder = PAMDecoder::from_reader(data);
+    /// let image = decoder.load().unwrap();
+    /// ```
+    pub fn from_reader(reader: R) -> PAMDecoder<R> {
+        PAMDecoder{stream : Stream::new(reader)}
+    }
+
+    /// Sets the limits on the size of images loaded after this.
+    ///
+    /// Images larger than the limits are rejected with `LimitExceeded` as soon as their header is
+    /// read. See `Limits` for details.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.stream.set_limits(limits);
+    }
+
+    /// Loads a pam file.
+    ///
+    /// Will load a pam file with any number of channels. The file extension does not matter.
+    ///
+    /// If the file holds more than one image this loads the next one, so calling it again will
+    /// load the image after it. The decoder can also be used as an `Iterator` over the images.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::fs::File;
+    /// # use std::io::prelude::*;
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// # let mut file = File::create("image.pam").unwrap();
+    /// # let _ = file.write(b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\n\
+    /// #                       TUPLTYPE GRAYSCALE\nENDHDR\n\x00\xff");
+    /// let mut decoder = PAMDecoder::new("image.pam").unwrap();
+    /// let image = decoder.load().unwrap();
+    /// # let _ = fs::remove_file("image.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method will return all general file IO errors that can be raised by file read
+    /// operations. Additionally, it will return an error if the image type is not pam, if there are
+    /// no images left in the file, as well as all file header parsing errors. A `Truncated` error
+    /// is returned if the file ends before the end of the raster.
+    pub fn load(&mut self) ->  Result<PAMImage, Error> {
+        let header = self.next_header()?;
+        let dat = load_raster(&mut self.stream, &header)?;
+
+        Ok(PAMImage{width:header.width, height:header.height, channels:header.channels, dat,
+                    depth:header.depth, max_val:header.max_val, tuple_type:header.tuple_type,
+                    comments:header.comments})
+    }
+
+    /// Loads a pam file even if it ends part way through the raster.
+    ///
+    /// This works the same as `load` except that a raster cut short doesn't cause an error.
+    /// Instead the missing samples are set to 0 and the returned `PartialImage` records how many
+    /// rows were read in full. This is useful for showing as much as possible of a file that is
+    /// still being downloaded or was damaged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// let data:&[u8] = b"P7\nWIDTH 1\nHEIGHT 2\nDEPTH 2\nMAXVAL 255\nENDHDR\n\x00\xff\x00";
+    /// let mut decoder = PAMDecoder::from_reader(data);
+    /// let partial = decoder.load_partial().unwrap();
+    /// assert!(!partial.complete);
+    /// assert_eq!(partial.rows, 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for `Truncated` errors in the raster.
+    pub fn load_partial(&mut self) -> Result<PartialImage<PAMImage>, Error> {
+        let header = self.next_header()?;
+        let (dat, rows) = load_raster_partial(&mut self.stream, &header)?;
+        let complete = rows == header.height;
+
+        let image = PAMImage{width:header.width, height:header.height, channels:header.channels, dat,
+                             depth:header.depth, max_val:header.max_val, tuple_type:header.tuple_type,
+                             comments:header.comments};
+        Ok(PartialImage{image, rows, complete})
+    }
+
+    /// Loads a pam file into a buffer provided by the caller.
+    ///
+    /// This works the same as `load` except that the samples are written to `buf` instead of a new
+    /// `Vec`, so one buffer can be reused for every image in a stream. `buf` must be exactly the
+    /// size of the image data, in the same layout as `PAMImage::dat`. The header of the image is
+    /// returned so its size and max value are known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// let data:&[u8] = b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\nENDHDR\n\x01\x02\
+    ///                   P7\nWIDTH 1\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\nENDHDR\n\x03\x04";
+    /// let mut decoder = PAMDecoder::from_reader(data);
+    /// let mut frame = [0u8; 2];
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [1, 2]);
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [3, 4]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`. A `DimensionMismatch` error is returned if `buf`
+    /// isn't the size of the image, in which case nothing is read and the image can be loaded
+    /// again with a buffer of the right size.
+    pub fn decode_into(&mut self, buf: &mut [u8]) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        check_buffer(&header, buf)?;
+        self.stream.skip_header(&header);
+        load_raster_into(&mut self.stream, &header, buf)?;
+        Ok(header)
+    }
+
+    /// Reads the header of the next image, checking that it is a pam image within the limits.
+    fn next_header(&mut self) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        self.stream.skip_header(&header);
+        Ok(header)
+    }
+
+    /// Reads the header of the next image without moving past it, checking that it is a pam
+    /// image. The size of the image isn't checked against the limits.
+    fn peek_header(&mut self) -> Result<ImageHeader, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.peek_header()?;
+
+        // check the magic number.
+        if header.image_type != ImageType::PAM {
+            return Result::Err(Error::BadMagicNumber{expected: Some("pam")});
+        }
+        Ok(header)
+    }
+
+    /// Loads a pam file and rescales it to the full range of its bit depth.
+    ///
+    /// Files can use any max value from 1 to 65535. This loads the file the same way as `load`
+    /// and then scales every sample so that the max value becomes 255 for eight-bit images or
+    /// 65535 for sixteen-bit images. The `max_val` of the returned image is set to match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::fs::File;
+    /// # use std::io::prelude::*;
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// # let mut file = File::create("image_rescaled.pam").unwrap();
+    /// # let _ = file.write(b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 1\nMAXVAL 1\nENDHDR\n\x00\x01");
+    /// let mut decoder = PAMDecoder::new("image_rescaled.pam").unwrap();
+    /// let image = decoder.load_rescaled().unwrap();
+    /// assert_eq!(image.dat, vec![0, 255]);
+    /// # let _ = fs::remove_file("image_rescaled.pam");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`.
+    pub fn load_rescaled(&mut self) -> Result<PAMImage, Error> {
+        let mut image = self.load()?;
+        rescale(&mut image.dat, &image.depth, image.max_val);
+        image.max_val = full_max_val(&image.depth);
+        Ok(image)
+    }
+
+    /// Starts loading the next image one row at a time.
+    ///
+    /// The header is read straight away and the raster is read as each row is requested, so only
+    /// one row of the image needs to be in memory at once. See the `rows` module for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pam::PAMDecoder;
+    ///
+    /// let data:&[u8] = b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\n\
+    ///                    TUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n\x00\xff\xff\xff";
+    /// let mut decoder = PAMDecoder::from_reader(data);
+    /// for row in decoder.rows().unwrap() {
+    ///     let row = row.unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same header errors as `load`. Errors in the raster are returned when the
+    /// row is read.
+    pub fn rows(&mut self) -> Result<Rows<'_, R>, Error> {
+        let header = self.next_header()?;
+        Ok(Rows::new(&mut self.stream, header))
+    }
+}
+
+impl<R: Read> Iterator for PAMDecoder<R> {
+    type Item = Result<PAMImage, Error>;
+
+    /// Loads the next image in the file.
+    ///
+    /// A file can hold several pam images one after another. Each call loads the next one until
+    /// the end of the file is reached. If an image can't be loaded the error is returned and no
+    /// more images will be read.
+    fn next(&mut self) -> Option<Result<PAMImage, Error>> {
+        match self.stream.at_end() {
+            Ok(true) => None,
+            Ok(false) => {
+                let image = self.load();
+                if image.is_err() {
+                    self.stream.finish();
+                }
+                Some(image)
+            },
+            Err(e) => {
+                self.stream.finish();
+                Some(Result::Err(Error::Io(e)))
+            },
+        }
+    }
+}
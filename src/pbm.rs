@@ -1,6 +1,8 @@
 //! Provides features for saving and loading images in the Portable BitMap format.
 //!
 //! The pbm (Portable BitMap) format is black and white only and all pixels are either 0 or 1.
+//! Binary files pack eight pixels into each byte with the first pixel in the most significant
+//! bit, and every row is padded out to a whole byte.
 //!
 //! # Examples
 //!
@@ -10,32 +12,47 @@
 //! use netbpm::{Mode,Image};
 //!
 //! let dat:[u8;4] = [1,0,0,1];
-//! let mut encoder = PBMEncoder::new("test_file.pbm");
+//! let mut encoder = PBMEncoder::new("test_file.pbm").unwrap();
 //! encoder.save(&dat, 2, 2, Mode::ASCII).unwrap();
 //!
-//! let mut decoder = PBMDecoder::new("test_file.pbm");
+//! let mut decoder = PBMDecoder::new("test_file.pbm").unwrap();
 //! let image = decoder.load().unwrap();
 //! # let _ = fs::remove_file("test_file.pbm");
 //! ```
 
-use std::io;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
-use tools::{get_header, ImageType};
+use std::io::Seek;
+use tools::{check_buffer, check_len, check_region, check_stride, encoder_row_len, load_raster, load_raster_into, load_raster_lenient, load_raster_partial, load_region, ImageHeader, Stream};
+use rows::{write_image, RowWriter, Rows};
+use Error;
+use ImageType;
+use Limits;
 use Mode;
+use BitDepth;
+use Comment;
 use Image;
+use LenientImage;
+use PartialImage;
 
 /// Encodes an image as a pbm file.
-pub struct PBMEncoder {
-    f: File,
+///
+/// The encoder can write to anything that implements `Write`, such as a file, a socket, or a
+/// `Vec<u8>` in memory. `new` and `append` are provided as shortcuts for writing to files.
+pub struct PBMEncoder<W: Write = File> {
+    f: W,
+    comments: Vec<Comment>,
 }
 
 /// Decodes an image in the pbm format.
-pub struct PBMDecoder {
-    f: File,
+///
+/// The decoder can read from anything that implements `Read`, such as a file, a pipe, or a byte
+/// slice in memory. `new` is provided as a shortcut for reading from files.
+pub struct PBMDecoder<R: Read = File> {
+    stream: Stream<R>,
 }
 
-impl PBMEncoder {
+impl PBMEncoder<File> {
     /// Create a new `PBMEncoder`
     ///
     /// Creates a new `PBMEncoder` by creating a file with the specified name. The file extension
@@ -47,12 +64,99 @@ impl PBMEncoder {
     ///  # use std::fs;
     /// use netbpm::pbm::PBMEncoder;
     ///
-    /// let encoder = PBMEncoder::new("pbm_file.pbm");
+    /// let encoder = PBMEncoder::new("pbm_file.pbm").unwrap();
     /// # let _ = fs::remove_file("pbm_file.pbm");
     /// ```
-    pub fn new(file_name: &str) -> PBMEncoder {
-        let file = File::create(file_name).unwrap();
-        PBMEncoder{f : file}
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be created.
+    pub fn new(file_name: &str) -> Result<PBMEncoder, Error> {
+        let file = File::create(file_name)?;
+        Ok(PBMEncoder{f : file, comments: vec![]})
+    }
+
+    /// Create a `PBMEncoder` that adds images to the end of a file.
+    ///
+    /// The standard allows several images to be stored one after another in the same file. Each
+    /// call to `save` writes a complete image, so an encoder made with this method can be used to
+    /// add frames to an existing stream. The file is created if it doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::pbm::{PBMEncoder,PBMDecoder};
+    /// use netbpm::Mode;
+    ///
+    /// let frame:[u8;2] = [1,0];
+    /// let mut encoder = PBMEncoder::new("frames.pbm").unwrap();
+    /// encoder.save(&frame, 2, 1, Mode::BINARY).unwrap();
+    ///
+    /// let mut encoder = PBMEncoder::append("frames.pbm").unwrap();
+    /// encoder.save(&frame, 2, 1, Mode::BINARY).unwrap();
+    ///
+    /// let decoder = PBMDecoder::new("frames.pbm").unwrap();
+    /// assert_eq!(decoder.count(), 2);
+    /// # let _ = fs::remove_file("frames.pbm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened or created.
+    pub fn append(file_name: &str) -> Result<PBMEncoder, Error> {
+        let file = OpenOptions::new().append(true).create(true).open(file_name)?;
+        Ok(PBMEncoder{f : file, comments: vec![]})
+    }
+}
+
+impl<W: Write> PBMEncoder<W> {
+    /// Create a new `PBMEncoder` that writes to any `Write`.
+    ///
+    /// Each call to `save` writes a complete image, so several images can be written to the same
+    /// stream one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMEncoder;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let encoder = PBMEncoder::from_writer(&mut buffer);
+    /// ```
+    pub fn from_writer(writer: W) -> PBMEncoder<W> {
+        PBMEncoder{f : writer, comments: vec![]}
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.f
+    }
+
+    /// Sets the comments written in the header of every image saved after this.
+    ///
+    /// Each comment is written on its own line at its position in the header. The comments stay
+    /// set until this is called again, so pass an empty slice to stop writing them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMEncoder;
+    /// use netbpm::{Comment,Mode};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PBMEncoder::from_writer(&mut buffer);
+    /// encoder.set_comments(&[Comment{text: "camera 2".to_string(), position: 0}]);
+    /// encoder.save(&[1, 0], 2, 1, Mode::BINARY).unwrap();
+    /// ```
+    pub fn set_comments(&mut self, comments: &[Comment]) {
+        self.comments = comments.to_vec();
+    }
+
+    /// Builds the header for an image, including the comments set on the encoder.
+    fn header(&self, width: u32, height: u32, mode: Mode) -> ImageHeader {
+        ImageHeader{width, height, dat_start: 0, image_type: ImageType::PBM, depth: BitDepth::EIGHT, max_val: 1,
+                    mode, channels: 1, tuple_type: String::new(), comments: self.comments.clone()}
     }
 
     /// Saves image data to the file stored by the `PBMEncoder`.
@@ -79,135 +183,87 @@ impl PBMEncoder {
     ///                       0,0,0,0,0,0,
     ///                       0,0,0,0,0,0];
     /// 
-    /// let mut encoder = PBMEncoder::new("test_file.pbm");
+    /// let mut encoder = PBMEncoder::new("test_file.pbm").unwrap();
     /// encoder.save(&data, 6, 10, Mode::ASCII).unwrap();
     /// ```
     ///
     /// # Errors
     ///
-    /// Aside from the regular errors associated with file access this function will throw an
-    /// error if the user attempts to save an image more than 70 pixels wide in ASCII mode.
-    pub fn save(&mut self, dat: &[u8], height: u32, width: u32, mode: Mode) -> Result<(), io::Error> {
-        Ok(
-            match mode {
-                Mode::ASCII => try!(self.save_ascii(dat, height, width)),
-                Mode::BINARY => try!(self.save_binary(dat, height, width)),
-            }
-        )
+    /// Returns `DimensionMismatch` if `dat` doesn't hold exactly one byte for each pixel of the
+    /// image. Any error from writing to the underlying writer is also returned.
+    pub fn save(&mut self, dat: &[u8], width: u32, height: u32, mode: Mode) -> Result<(), Error> {
+        let row_len = encoder_row_len(width, 1, 1)?;
+        check_len(dat, row_len, height)?;
+        let header = self.header(width, height, mode);
+        write_image(&mut self.f, header, dat, row_len)
     }
 
-    /// Saves a pbm file in ASCII format.
+    /// Saves image data whose rows are spaced out in a larger buffer.
+    ///
+    /// This works the same as `save` except that each row starts `stride` bytes after the start
+    /// of the row before it, rather than straight after it. Any bytes between the rows are
+    /// skipped. This saves part of a larger image, or an image with padded rows, without copying
+    /// it first.
     ///
     /// # Examples
     ///
-    /// P1
-    /// # This is an example bitmap of the letter "J"
-    /// 6 10
-    /// 0 0 0 0 1 0
-    /// 0 0 0 0 1 0
-    /// 0 0 0 0 1 0
-    /// 0 0 0 0 1 0
-    /// 0 0 0 0 1 0
-    /// 0 0 0 0 1 0
-    /// 1 0 0 0 1 0
-    /// 0 1 1 1 0 0
-    /// 0 0 0 0 0 0
-    /// 0 0 0 0 0 0
-    ///
-    /// P1 = file's magic number
-    /// # foo = comment
-    /// num num = width and height (in that order)
-    fn save_ascii(&mut self, dat: &[u8], width: u32, height: u32) -> Result<(), io::Error> {
-        // In theory we can ignore this with no downside but it would no longer be conformant.
-        if width > 70 {
-            return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Width can not be greater than 70 for ascii pbm files."));
-        }
-        // write the header information.
-        try!(self.f.write_fmt(format_args!("P1\n{} {}\n",width,height)));
-        // track if we need to insert a new line character at the end of a row.
-        let mut nl = 1;
-        // write the actual image data.
-        for val in dat {
-            match *val {
-                0 => try!(self.f.write_all(b"0")),
-                _ => try!(self.f.write_all(b"1")),
-            };
-            if nl < width {
-                try!(self.f.write_all(b" "));
-                nl += 1;
-            } else {
-                try!(self.f.write_all(b"\n"));
-                nl = 1;
-            }
-        }
-        Ok(())
+    /// ```
+    /// use netbpm::pbm::PBMEncoder;
+    /// use netbpm::Mode;
+    ///
+    /// // Save the left half of a 4x2 image.
+    /// const data:[u8;8] = [1,0, 1,1,
+    ///                      0,1, 1,1];
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PBMEncoder::from_writer(&mut buffer);
+    /// encoder.save_stride(&data, 2, 2, 4, Mode::ASCII).unwrap();
+    /// assert_eq!(buffer, b"P1\n2 2\n1 0\n0 1\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if `stride` is less than the width and `DimensionMismatch` if
+    /// `dat` ends before the end of the last row. Any error from writing to the underlying writer
+    /// is also returned.
+    pub fn save_stride(&mut self, dat: &[u8], width: u32, height: u32, stride: usize, mode: Mode) -> Result<(), Error> {
+        let row_len = encoder_row_len(width, 1, 1)?;
+        check_stride(dat, row_len, height, stride)?;
+        let header = self.header(width, height, mode);
+        write_image(&mut self.f, header, dat, stride)
     }
 
-    /// saves a pbm file in binary format
-    ///
-    /// The header is actually still in ASCII and is the same as the header above, except with
-    /// a magic number of P4
-    ///
-    /// The pixels are stored as single bits with "don't care" bits added as padding to a byte at
-    /// the end of the row. The data shown above would look like this in binary. White space has
-    /// been added for clarity but there are no spaces or new lines in the actual data.
-    ///
-    /// 0000 1000
-    /// 0000 1000
-    /// 0000 1000
-    /// 0000 1000
-    /// 0000 1000
-    /// 0000 1000
-    /// 1000 1000
-    /// 0111 0000
-    /// 0000 0000
-    /// 0000 0000
-    fn save_binary(&mut self, dat: &[u8], width: u32, height: u32) -> Result<(), io::Error> {
-        // write the header
-        try!(self.f.write_fmt(format_args!("P4\n{} {}\n",width,height)));
-        // If our width is not a multiple of 8 we will need to add some "don't care" bits to
-        // the final byte in each row.
-        let padding = 8 - (width % 8);
-        // New Line tracker. We don't actually add a new line character but we need to add any
-        // padding to the byte at the end of a row
-        let mut nl = 1;
-        // tracks if we should start a new byte
-        let mut byte_pos = 0;
-        let mut tmp:[u8;1] = [0];
-        // write our image data.
-        for val in dat {
-            tmp[0] = tmp[0] << 1;
-            tmp[0] += match *val {
-                0 => 0,
-                _ => 1,
-            };
-            byte_pos += 1;
-            // If we've reached the end of a byte
-            if byte_pos == 8 {
-                try!(self.f.write(&tmp));
-                tmp[0] = 0;
-                byte_pos = 0;
-            }
-            // If we haven't reached the end of a row, keep going.
-            // If we have reached the end of a row, add any padding bits to the final byte and then
-            // write it to the file.
-            if nl < width {
-                nl += 1;
-            } else {
-                // Only write a padded byte if the width is not a multiple of 8.
-                if byte_pos != 0 {
-                    tmp[0] = tmp[0] << padding;
-                    try!(self.f.write(&tmp));
-                }
-                nl = 1;
-                byte_pos = 0;
-            }
-        }
-        Ok(())
+    /// Starts saving an image one row at a time.
+    ///
+    /// The header is written straight away from the width, height, and `Mode`. Each row is then
+    /// written with `write_row` on the returned `RowWriter`, so the whole image never needs to be
+    /// in memory at once. Rows have one byte for each pixel and any value greater than 0 is
+    /// recorded as 1. Long rows in ASCII mode are split over several lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMEncoder;
+    /// use netbpm::Mode;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PBMEncoder::from_writer(&mut buffer);
+    /// let mut writer = encoder.rows(2, 2, Mode::BINARY).unwrap();
+    /// writer.write_row(&[1, 0]).unwrap();
+    /// writer.write_row(&[0, 1]).unwrap();
+    /// writer.finish().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the header.
+    pub fn rows(&mut self, width: u32, height: u32, mode: Mode) -> Result<RowWriter<'_, W>, Error> {
+        let header = self.header(width, height, mode);
+        RowWriter::new(&mut self.f, header)
     }
 }
 
-impl PBMDecoder {
+impl PBMDecoder<File> {
     /// Create a new `PBMDecoder`
     ///
     /// Creates a new `PBMDecoder` that reads from the specified file. The file extension does not
@@ -221,12 +277,44 @@ impl PBMDecoder {
     /// use netbpm::pbm::PBMDecoder;
     ///
     /// # let _ = File::create("saved_file.pbm");
-    /// let decoder = PBMDecoder::new("saved_file.pbm");
+    /// let decoder = PBMDecoder::new("saved_file.pbm").unwrap();
     ///  # let _ = fs::remove_file("saved_file.pbm");
     /// ```
-    pub fn new(file_name: &str) -> PBMDecoder {
-        let file = File::open(file_name).unwrap();
-        PBMDecoder{f : file}
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened.
+    pub fn new(file_name: &str) -> Result<PBMDecoder, Error> {
+        let file = File::open(file_name)?;
+        Ok(PBMDecoder{stream : Stream::new(file)})
+    }
+}
+
+impl<R: Read> PBMDecoder<R> {
+    /// Create a new `PBMDecoder` that reads from any `Read`.
+    ///
+    /// Only as much data as is needed is read for each image, so images can be loaded from a pipe
+    /// or socket as they arrive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMDecoder;
+    ///
+    /// let data:&[u8] = b"P1\n2 2\n1 0\n0 1\n";
+    /// let mut decoder = PBMDecoder::from_reader(data);
+    /// let image = decoder.load().unwrap();
+    /// ```
+    pub fn from_reader(reader: R) -> PBMDecoder<R> {
+        PBMDecoder{stream : Stream::new(reader)}
+    }
+
+    /// Sets the limits on the size of images loaded after this.
+    ///
+    /// Images larger than the limits are rejected with `LimitExceeded` as soon as their header is
+    /// read. See `Limits` for details.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.stream.set_limits(limits);
     }
 
     /// Loads a pbm file.
@@ -235,6 +323,9 @@ impl PBMDecoder {
     /// parse the header information before handing it off to more specalized methods to load the
     /// binary or ASCII data.
     ///
+    /// If the file holds more than one image this loads the next one, so calling it again will
+    /// load the image after it. The decoder can also be used as an `Iterator` over the images.
+    ///
     /// # Examples
     ///
     /// ```
@@ -246,7 +337,7 @@ impl PBMDecoder {
     ///
     /// # let mut file = File::create("image.pbm").unwrap();
     /// # let _ = file.write(b"P1\n2 2\n1 0\n0 1");
-    /// let mut decoder = PBMDecoder::new("image.pbm");
+    /// let mut decoder = PBMDecoder::new("image.pbm").unwrap();
     /// let image = decoder.load().unwrap();
     /// # let _ = fs::remove_file("image.pbm");
     /// ```
@@ -254,65 +345,228 @@ impl PBMDecoder {
     /// # Errors
     ///
     /// This method will return all general file IO errors that can be raised by file read
-    /// operations. Additionally, it will return an error if the magic number does not start with
-    /// P, if the magic number is not P1 or P4, or if there is a non-numeric of whitespace
-    /// character in the size line of the header.
-    pub fn load(&mut self) ->  Result<Image, io::Error> {
-        let mut all_data:Vec<u8> = vec![];
-        try!(self.f.read_to_end(&mut all_data));
-        let header = try!(get_header(&all_data));
+    /// operations. Additionally, it will return an error if the magic number does not start with P,
+    /// if the magic number is not P1 or P4, if there are no images left in the file, or if there is
+    /// a non-numeric of whitespace character in the size line of the header. A `Truncated` error is
+    /// returned if the file ends before the end of the raster, and an `UnexpectedRasterCharacter`
+    /// error if an ASCII raster holds anything other than pixels, whitespace, and comments.
+    pub fn load(&mut self) ->  Result<Image, Error> {
+        let header = self.next_header()?;
+        let dat = load_raster(&mut self.stream, &header)?;
+
+        Ok(Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                 comments: header.comments})
+    }
+
+    /// Loads a pbm file even if it ends part way through the raster.
+    ///
+    /// This works the same as `load` except that a raster cut short doesn't cause an error.
+    /// Instead the missing samples are set to 0 and the returned `PartialImage` records how many
+    /// rows were read in full. This is useful for showing as much as possible of a file that is
+    /// still being downloaded or was damaged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMDecoder;
+    ///
+    /// let data:&[u8] = b"P1\n2 2\n1 0\n0";
+    /// let mut decoder = PBMDecoder::from_reader(data);
+    /// let partial = decoder.load_partial().unwrap();
+    /// assert!(!partial.complete);
+    /// assert_eq!(partial.rows, 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for `Truncated` errors in the raster.
+    pub fn load_partial(&mut self) -> Result<PartialImage<Image>, Error> {
+        let header = self.next_header()?;
+        let (dat, rows) = load_raster_partial(&mut self.stream, &header)?;
+        let complete = rows == header.height;
+
+        let image = Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                          comments: header.comments};
+        Ok(PartialImage{image, rows, complete})
+    }
+
+    /// Loads a pbm file, working around problems that `load` would return as errors.
+    ///
+    /// Characters other than pixels, whitespace, and comments in an ASCII raster are skipped, a
+    /// raster that ends early is filled with white pixels, and anything after the raster that isn't
+    /// another image is skipped. Each kind of problem found is reported as a `Warning` alongside
+    /// the image, so damaged files can be loaded without the damage going unnoticed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMDecoder;
+    /// use netbpm::Warning;
+    ///
+    /// let data:&[u8] = b"P1\n3 1\n1 x 0 1\n";
+    /// let mut decoder = PBMDecoder::from_reader(data);
+    /// let lenient = decoder.load_lenient().unwrap();
+    /// assert_eq!(lenient.image.dat, vec![1, 0, 1]);
+    /// assert_eq!(lenient.warnings[0], Warning::UnexpectedCharacters{count: 1, offset: 2});
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for errors in the raster.
+    pub fn load_lenient(&mut self) -> Result<LenientImage<Image>, Error> {
+        let header = self.next_header()?;
+        let (dat, warnings) = load_raster_lenient(&mut self.stream, &header)?;
+
+        let image = Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                          comments: header.comments};
+        Ok(LenientImage{image, warnings})
+    }
+
+    /// Loads a pbm file into a buffer provided by the caller.
+    ///
+    /// This works the same as `load` except that the samples are written to `buf` instead of a new
+    /// `Vec`, so one buffer can be reused for every image in a stream. `buf` must be exactly the
+    /// size of the image data, in the same layout as `Image::dat`. The header of the image is
+    /// returned so its size and max value are known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMDecoder;
+    ///
+    /// let data:&[u8] = b"P1\n2 1\n1 0\nP1\n2 1\n0 1\n";
+    /// let mut decoder = PBMDecoder::from_reader(data);
+    /// let mut frame = [0u8; 2];
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [1, 0]);
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [0, 1]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`. A `DimensionMismatch` error is returned if `buf`
+    /// isn't the size of the image, in which case nothing is read and the image can be loaded
+    /// again with a buffer of the right size.
+    pub fn decode_into(&mut self, buf: &mut [u8]) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        check_buffer(&header, buf)?;
+        self.stream.skip_header(&header);
+        load_raster_into(&mut self.stream, &header, buf)?;
+        Ok(header)
+    }
+
+    /// Reads the header of the next image, checking that it is a pbm image within the limits.
+    fn next_header(&mut self) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        self.stream.skip_header(&header);
+        Ok(header)
+    }
+
+    /// Reads the header of the next image without moving past it, checking that it is a pbm
+    /// image. The size of the image isn't checked against the limits.
+    fn peek_header(&mut self) -> Result<ImageHeader, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.peek_header()?;
 
         // check the magic number.
         if header.image_type != ImageType::PBM {
-            return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Input file is not a pbm file."));
+            return Result::Err(Error::BadMagicNumber{expected: Some("pbm")});
         }
+        Ok(header)
+    }
 
-        match header.mode {
-            Mode::ASCII => Ok(Image{width:header.width, height:header.height,
-                           dat:self.load_ascii(&all_data[header.dat_start..all_data.len()]),
-                           depth: header.depth}),
-            Mode::BINARY => Ok(Image{width:header.width, height:header.height,
-                           dat:self.load_binary(&all_data[header.dat_start..all_data.len()], header.width),
-                           depth: header.depth})
-        }
+    /// Starts loading the next image one row at a time.
+    ///
+    /// The header is read straight away and the raster is read as each row is requested, so only
+    /// one row of the image needs to be in memory at once. See the `rows` module for details. Pbm
+    /// rows have one byte for each pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pbm::PBMDecoder;
+    ///
+    /// let data:&[u8] = b"P1\n3 2\n1 0 1\n0 1 0\n";
+    /// let mut decoder = PBMDecoder::from_reader(data);
+    /// for row in decoder.rows().unwrap() {
+    ///     let row = row.unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same header errors as `load`. Errors in the raster are returned when the
+    /// row is read.
+    pub fn rows(&mut self) -> Result<Rows<'_, R>, Error> {
+        let header = self.next_header()?;
+        Ok(Rows::new(&mut self.stream, header))
     }
+}
 
-    /// Load image data stored in ASCII format.
-    fn load_ascii(&self, inpt: &[u8]) -> Vec<u8> {
-        let mut vals:Vec<u8> = vec![];
-        for x in inpt{
-            if *x == 48 {
-                vals.push(0);
-            } else if *x == 49 {
-                vals.push(1);
-            }
-        }
-        vals
+impl<R: Read + Seek> PBMDecoder<R> {
+    /// Loads a rectangle from a binary pbm file without reading the rest of the raster.
+    ///
+    /// The region is `w` by `h` pixels with its top left corner at `x`, `y`. Every row of a binary
+    /// raster takes up the same number of bytes, so the decoder seeks straight to each row of the
+    /// region and reads only the pixels inside it. Rows are packed eight pixels to a byte, so the
+    /// region doesn't need to start or end on a byte boundary. This makes it possible to take small
+    /// crops out of very large files. Afterwards the decoder is left at the end of the image, so
+    /// the next image in the file can be loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use netbpm::pbm::PBMDecoder;
+    ///
+    /// let data:&[u8] = b"P4\n10 2\n\xf0\x00\x0f\xc0";
+    /// let mut decoder = PBMDecoder::from_reader(Cursor::new(data));
+    /// let region = decoder.decode_region(3, 0, 7, 2).unwrap();
+    /// assert_eq!(region.dat, vec![1, 0, 0, 0, 0, 0, 0,  0, 1, 1, 1, 1, 1, 1]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`. Additionally, it will return `InvalidParameter` if
+    /// the image is stored in ASCII or the region isn't inside the image. The limits set on the
+    /// decoder are checked against the size of the region rather than the whole image.
+    pub fn decode_region(&mut self, x: u32, y: u32, w: u32, h: u32) -> Result<Image, Error> {
+        let header = self.peek_header()?;
+        check_region(&header, self.stream.limits(), x, y, w, h)?;
+        self.stream.skip_header(&header);
+        let dat = load_region(&mut self.stream, &header, x, y, w, h)?;
+
+        Ok(Image{width: w, height: h, dat, depth: header.depth, max_val: header.max_val, comments: header.comments})
     }
+}
 
-    /// Load image data stored in binary format.
-    fn load_binary(&self, inpt: &[u8], width: u32) -> Vec<u8> {
-        let mut vals:Vec<u8> = vec![];
-        // Keep track of what index we're on, counted in bits.
-        let mut ind = 8;
-        // How much padding (if any) exists on bytes at the end of a row.
-        let padding = ((8 - (width % 8)) % 8) as u8;
-        // Use a mask to pick up individual bits.
-        let masks:[u8;8] = [128, 64, 32, 16, 8, 4, 2, 1];
-        for x in inpt {
-            for i in 0..8 {
-                let mv:u8 = 8 - i;
-                let v:u8 = (x & masks[i as usize]) >> (mv - 1);
-                if ind < width || mv > padding {
-                    vals.push(v);
+impl<R: Read> Iterator for PBMDecoder<R> {
+    type Item = Result<Image, Error>;
+
+    /// Loads the next image in the file.
+    ///
+    /// A file can hold several pbm images one after another. Each call loads the next one until
+    /// the end of the file is reached. If an image can't be loaded the error is returned and no
+    /// more images will be read.
+    fn next(&mut self) -> Option<Result<Image, Error>> {
+        match self.stream.at_end() {
+            Ok(true) => None,
+            Ok(false) => {
+                let image = self.load();
+                if image.is_err() {
+                    self.stream.finish();
                 }
-            }
-            if ind >= width {
-                ind = 8;
-            } else {
-                ind += 8;
-            }
+                Some(image)
+            },
+            Err(e) => {
+                self.stream.finish();
+                Some(Result::Err(Error::Io(e)))
+            },
         }
-        vals
     }
 }
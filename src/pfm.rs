@@ -0,0 +1,398 @@
+//! Provides features for saving and loading images in the Portable FloatMap format.
+//!
+//! The pfm (Portable FloatMap) format stores each sample as a 32-bit floating point number, which
+//! makes it useful for high dynamic range images and depth buffers. The magic number `PF` is used
+//! for color images with three samples per pixel and `Pf` for gray scale images with one.
+//!
+//! The header is the magic number, the width and height, and a scale factor. The sign of the
+//! scale gives the byte order of the samples: negative for little-endian and positive for
+//! big-endian. Unlike the other formats the rows are stored from the bottom of the image to the
+//! top. The decoder and encoder turn them around, so the samples of a `PFMImage` start at the top
+//! left corner like every other image in this crate.
+//!
+//! # Examples
+//!
+//! ```
+//! # use std::fs;
+//! use netbpm::pfm::{ByteOrder,PFMEncoder,PFMDecoder};
+//!
+//! // A 2x1 gray scale image with values outside of the usual 0 to 1 range.
+//! let dat:[f32;2] = [-0.5, 12.25];
+//! let mut encoder = PFMEncoder::new("test_file.pfm").unwrap();
+//! encoder.save(&dat, 2, 1, 1, ByteOrder::LITTLE).unwrap();
+//!
+//! let mut decoder = PFMDecoder::new("test_file.pfm").unwrap();
+//! let image = decoder.load().unwrap();
+//! assert_eq!(image.dat, vec![-0.5, 12.25]);
+//! # let _ = fs::remove_file("test_file.pfm");
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
+use tools::{check_limits, is_whitespace, location, Stream};
+use Error;
+use Limits;
+
+/// The order of the bytes in each sample of a pfm file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ByteOrder {
+    BIG,
+    LITTLE,
+}
+
+/// An image loaded from a pfm file.
+///
+/// The samples are stored row by row from the top of the image, with the red, green, and blue
+/// samples of each pixel next to each other in color images.
+pub struct PFMImage {
+    pub width:u32,
+    pub height:u32,
+    /// The number of samples in each pixel: 3 for `PF` images and 1 for `Pf` images.
+    pub channels:u32,
+    pub dat: Vec<f32>,
+    /// The byte order the samples were stored in.
+    pub byte_order: ByteOrder,
+    /// The size of the scale factor from the header. Its sign is recorded by `byte_order`.
+    pub scale: f32,
+}
+
+/// Encodes an image as a pfm file.
+///
+/// The encoder can write to anything that implements `Write`, such as a file, a socket, or a
+/// `Vec<u8>` in memory. `new` and `append` are provided as shortcuts for writing to files.
+pub struct PFMEncoder<W: Write = File> {
+    f: W,
+}
+
+/// Decodes an image in the pfm format.
+///
+/// The decoder can read from anything that implements `Read`, such as a file, a pipe, or a byte
+/// slice in memory. `new` is provided as a shortcut for reading from files.
+pub struct PFMDecoder<R: Read = File> {
+    stream: Stream<R>,
+}
+
+impl PFMEncoder<File> {
+    /// Create a new `PFMEncoder`
+    ///
+    /// Creates a new `PFMEncoder` by creating a file with the specified name. The file extension
+    /// is not important for using this module to encode/decode pfm images.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  # use std::fs;
+    /// use netbpm::pfm::PFMEncoder;
+    ///
+    /// let encoder = PFMEncoder::new("pfm_file.pfm").unwrap();
+    /// # let _ = fs::remove_file("pfm_file.pfm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be created.
+    pub fn new(file_name: &str) -> Result<PFMEncoder, Error> {
+        let file = File::create(file_name)?;
+        Ok(PFMEncoder{f : file})
+    }
+
+    /// Create a `PFMEncoder` that adds images to the end of a file.
+    ///
+    /// Each call to `save` writes a complete image, so an encoder made with this method can be
+    /// used to add frames to an existing stream. The file is created if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened or created.
+    pub fn append(file_name: &str) -> Result<PFMEncoder, Error> {
+        let file = OpenOptions::new().append(true).create(true).open(file_name)?;
+        Ok(PFMEncoder{f : file})
+    }
+}
+
+impl<W: Write> PFMEncoder<W> {
+    /// Create a new `PFMEncoder` that writes to any `Write`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pfm::PFMEncoder;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let encoder = PFMEncoder::from_writer(&mut buffer);
+    /// ```
+    pub fn from_writer(writer: W) -> PFMEncoder<W> {
+        PFMEncoder{f : writer}
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.f
+    }
+
+    /// Saves image data to the file stored by the `PFMEncoder`.
+    ///
+    /// This method takes the samples row by row from the top of the image, the width, the height,
+    /// the number of channels, and the `ByteOrder` to store the samples in. Images with 3
+    /// channels are saved as color `PF` files and images with 1 channel as gray scale `Pf` files.
+    /// The scale factor in the header is 1. Use `save_scale` to save a different one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pfm::{ByteOrder,PFMEncoder};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PFMEncoder::from_writer(&mut buffer);
+    /// encoder.save(&[1.0, 0.5, 0.25], 1, 1, 3, ByteOrder::BIG).unwrap();
+    /// assert!(buffer.starts_with(b"PF\n1 1\n1.0\n"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Aside from the regular errors associated with file access this function will return an
+    /// `InvalidParameter` error if the number of channels isn't 1 or 3 and a `DimensionMismatch`
+    /// error if there isn't one sample for each channel of each pixel.
+    pub fn save(&mut self, dat: &[f32], width: u32, height: u32, channels: u32, byte_order: ByteOrder) -> Result<(), Error> {
+        self.save_scale(dat, width, height, channels, byte_order, 1.0)
+    }
+
+    /// Saves image data with a scale factor other than 1.
+    ///
+    /// This works the same as `save` except that `scale` is written to the header, with its sign
+    /// set by the `ByteOrder`. The samples are saved as they are, so the scale only records what
+    /// they are relative to, such as the `scale` of a loaded `PFMImage`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pfm::{ByteOrder,PFMEncoder};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PFMEncoder::from_writer(&mut buffer);
+    /// encoder.save_scale(&[0.5], 1, 1, 1, ByteOrder::LITTLE, 2.5).unwrap();
+    /// assert!(buffer.starts_with(b"Pf\n1 1\n-2.5\n"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by `save` this will return an `InvalidParameter` error
+    /// if the scale isn't a finite number greater than 0.
+    pub fn save_scale(&mut self, dat: &[f32], width: u32, height: u32, channels: u32, byte_order: ByteOrder,
+                      scale: f32) -> Result<(), Error> {
+        if scale <= 0.0 || !scale.is_finite() {
+            return Result::Err(Error::InvalidParameter("The pfm scale factor must be a finite number greater than 0."));
+        }
+        let magic = match channels {
+            1 => "Pf",
+            3 => "PF",
+            _ => return Result::Err(Error::InvalidParameter("Pfm images must have 1 or 3 channels.")),
+        };
+        let row_len = width as usize * channels as usize;
+        let expected = row_len * height as usize;
+        if dat.len() != expected {
+            return Result::Err(Error::DimensionMismatch{expected: expected * 4, found: dat.len() * 4});
+        }
+        // The sign of the scale gives the byte order.
+        let scale = match byte_order {
+            ByteOrder::BIG => scale,
+            ByteOrder::LITTLE => -scale,
+        };
+        write!(self.f, "{}\n{} {}\n{:?}\n", magic, width, height, scale)?;
+
+        // The rows are written from the bottom of the image to the top.
+        let mut row:Vec<u8> = Vec::with_capacity(row_len * 4);
+        for samples in dat.chunks(row_len.max(1)).rev() {
+            row.clear();
+            for &sample in samples {
+                match byte_order {
+                    ByteOrder::BIG => row.extend_from_slice(&sample.to_bits().to_be_bytes()),
+                    ByteOrder::LITTLE => row.extend_from_slice(&sample.to_bits().to_le_bytes()),
+                }
+            }
+            self.f.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+impl PFMDecoder<File> {
+    /// Create a new `PFMDecoder`
+    ///
+    /// Creates a new `PFMDecoder` that reads from the specified file. The file extension does not
+    /// matter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::fs::File;
+    /// use netbpm::pfm::PFMDecoder;
+    ///
+    /// # let _ = File::create("saved_file.pfm");
+    /// let decoder = PFMDecoder::new("saved_file.pfm").unwrap();
+    ///  # let _ = fs::remove_file("saved_file.pfm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened.
+    pub fn new(file_name: &str) -> Result<PFMDecoder, Error> {
+        let file = File::open(file_name)?;
+        Ok(PFMDecoder{stream : Stream::new(file)})
+    }
+}
+
+impl<R: Read> PFMDecoder<R> {
+    /// Create a new `PFMDecoder` that reads from any `Read`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::pfm::PFMDecoder;
+    ///
+    /// let data:&[u8] = b"Pf\n1 1\n-1.0\n\x00\x00\x80\x3f";
+    /// let mut decoder = PFMDecoder::from_reader(data);
+    /// let image = decoder.load().unwrap();
+    /// assert_eq!(image.dat, vec![1.0]);
+    /// ```
+    pub fn from_reader(reader: R) -> PFMDecoder<R> {
+        PFMDecoder{stream : Stream::new(reader)}
+    }
+
+    /// Sets the limits on the size of images loaded after this.
+    ///
+    /// Images larger than the limits are rejected with `LimitExceeded` as soon as their header is
+    /// read. See `Limits` for details.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.stream.set_limits(limits);
+    }
+
+    /// Loads a pfm file.
+    ///
+    /// Will load both color `PF` and gray scale `Pf` files. The rows are turned around so that the
+    /// samples start at the top of the image, and each sample is converted from the byte order
+    /// given by the scale factor.
+    ///
+    /// If the file holds more than one image this loads the next one, so calling it again will
+    /// load the image after it. The decoder can also be used as an `Iterator` over the images.
+    ///
+    /// # Errors
+    ///
+    /// This method will return all general file IO errors that can be raised by file read
+    /// operations. Additionally, it will return an error if the image type is not pfm, if there are
+    /// no images left in the file, if the header is malformed, or if the file ends before the end
+    /// of the image.
+    pub fn load(&mut self) -> Result<PFMImage, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+
+        // check the magic number.
+        let channels = match (self.stream.next_byte()?, self.stream.next_byte()?) {
+            (Some(b'P'), Some(b'F')) => 3,
+            (Some(b'P'), Some(b'f')) => 1,
+            _ => return Result::Err(Error::BadMagicNumber{expected: Some("pfm")}),
+        };
+        // The header read so far, kept to find where any errors are.
+        let mut header:Vec<u8> = vec![b'P', if channels == 3 { b'F' } else { b'f' }];
+        match self.stream.next_byte()? {
+            Some(c) if is_whitespace(c) => header.push(c),
+            Some(c) => return Result::Err(Error::UnexpectedCharacter{character: c, location: location(&header, 2)}),
+            None => return Result::Err(Error::Truncated),
+        }
+
+        let width = parse_value::<u32>(&self.header_token(&mut header)?, "width")?;
+        let height = parse_value::<u32>(&self.header_token(&mut header)?, "height")?;
+        let scale = parse_value::<f32>(&self.header_token(&mut header)?, "scale")?;
+        if scale == 0.0 || !scale.is_finite() {
+            return Result::Err(Error::InvalidHeader("The pfm scale factor must be a number other than 0.".to_string()));
+        }
+        let byte_order = if scale < 0.0 { ByteOrder::LITTLE } else { ByteOrder::BIG };
+
+        check_limits(self.stream.limits(), width, height, channels as u32, 4)?;
+        let row_len = width as usize * channels;
+        let len = row_len * height as usize * 4;
+        let raster = self.stream.take(len)?;
+        if raster.len() < len {
+            return Result::Err(Error::Truncated);
+        }
+
+        // The rows are stored from the bottom of the image to the top.
+        let mut dat:Vec<f32> = Vec::with_capacity(len / 4);
+        for row in raster.chunks((row_len * 4).max(1)).rev() {
+            for bytes in row.chunks(4) {
+                let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+                let bits = match byte_order {
+                    ByteOrder::BIG => u32::from_be_bytes(bytes),
+                    ByteOrder::LITTLE => u32::from_le_bytes(bytes),
+                };
+                dat.push(f32::from_bits(bits));
+            }
+        }
+
+        Ok(PFMImage{width, height, channels: channels as u32, dat, byte_order, scale: scale.abs()})
+    }
+
+    /// Reads the next value of the header.
+    ///
+    /// Values are separated by whitespace. The single whitespace character after the value is
+    /// read as well, so after the scale factor the stream is left at the start of the raster.
+    fn header_token(&mut self, header: &mut Vec<u8>) -> Result<String, Error> {
+        let mut token = String::new();
+        loop {
+            let c = match self.stream.next_byte()? {
+                Some(c) => c,
+                None => return Result::Err(Error::Truncated),
+            };
+            if is_whitespace(c) {
+                header.push(c);
+                if !token.is_empty() {
+                    return Ok(token);
+                }
+            } else if token.len() > 64 {
+                // A header value this long can't be valid.
+                return Result::Err(Error::InvalidHeader("Malformed pfm header.".to_string()));
+            } else if c.is_ascii_graphic() {
+                header.push(c);
+                token.push(c as char);
+            } else {
+                return Result::Err(Error::UnexpectedCharacter{character: c, location: location(header, header.len())});
+            }
+        }
+    }
+}
+
+/// Parses a value from the pfm header.
+fn parse_value<T: ::std::str::FromStr>(token: &str, name: &str) -> Result<T, Error> {
+    match token.parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(_) => Result::Err(Error::InvalidHeader(format!("Invalid {} in pfm header: {}", name, token))),
+    }
+}
+
+impl<R: Read> Iterator for PFMDecoder<R> {
+    type Item = Result<PFMImage, Error>;
+
+    /// Loads the next image in the file.
+    ///
+    /// A file can hold several pfm images one after another. Each call loads the next one until
+    /// the end of the file is reached. If an image can't be loaded the error is returned and no
+    /// more images will be read.
+    fn next(&mut self) -> Option<Result<PFMImage, Error>> {
+        match self.stream.at_end() {
+            Ok(true) => None,
+            Ok(false) => {
+                let image = self.load();
+                if image.is_err() {
+                    self.stream.finish();
+                }
+                Some(image)
+            },
+            Err(e) => {
+                self.stream.finish();
+                Some(Result::Err(Error::Io(e)))
+            },
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Provides an image container that knows the type of its pixels.
+//!
+//! `Image` stores samples as plain bytes, so the number of channels and the bit depth have to be
+//! tracked separately and pixels have to be found with index arithmetic. `ImageBuffer` instead
+//! stores one value per pixel, such as `Rgb<u8>` or `Luma<u16>`, and gives access to pixels by
+//! their coordinates, by row, or by iterating over them.
+//!
+//! # Examples
+//!
+//! ```
+//! use netbpm::ppm::PPMDecoder;
+//! use netbpm::pixel::{ImageBuffer, Rgb};
+//!
+//! let data:&[u8] = b"P3\n2 1\n255\n255 0 0  0 0 255\n";
+//! let mut decoder = PPMDecoder::from_reader(data);
+//! let mut image = ImageBuffer::<Rgb<u8>>::from_image(&decoder.load().unwrap()).unwrap();
+//! assert_eq!(image.get_pixel(1, 0), Rgb([0, 0, 255]));
+//! image.put_pixel(0, 0, Rgb([0, 255, 0]));
+//! ```
+
+use std::fmt::Debug;
+use std::slice;
+use pam::PAMImage;
+use tools::depth_for;
+use BitDepth;
+use Comment;
+use Error;
+use Image;
+
+/// A type that can be used for a single sample of a pixel.
+pub trait Sample: Copy + Default + PartialEq + Debug {
+    /// The bit depth images with this type of sample are stored with.
+    const DEPTH: BitDepth;
+
+    /// The largest value the bit depth can hold.
+    const MAX_VAL: u16;
+
+    /// Reads a sample from the start of `dat`, which is in the layout used by the files.
+    fn from_bytes(dat: &[u8]) -> Self;
+
+    /// Adds the sample to `out` in the layout used by the files.
+    fn push_bytes(self, out: &mut Vec<u8>);
+}
+
+impl Sample for u8 {
+    const DEPTH: BitDepth = BitDepth::EIGHT;
+    const MAX_VAL: u16 = 255;
+
+    fn from_bytes(dat: &[u8]) -> u8 {
+        dat[0]
+    }
+
+    fn push_bytes(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+}
+
+impl Sample for u16 {
+    const DEPTH: BitDepth = BitDepth::SIXTEEN;
+    const MAX_VAL: u16 = 65535;
+
+    fn from_bytes(dat: &[u8]) -> u16 {
+        ((dat[0] as u16) << 8) + dat[1] as u16
+    }
+
+    fn push_bytes(self, out: &mut Vec<u8>) {
+        out.push((self >> 8) as u8);  // most significant byte first.
+        out.push((self & 255) as u8); // least significant byte second.
+    }
+}
+
+/// A type that can be used for the pixels of an `ImageBuffer`.
+pub trait Pixel: Copy + Default + PartialEq + Debug {
+    /// The type of each sample in the pixel.
+    type Subpixel: Sample;
+
+    /// The number of samples in the pixel.
+    const CHANNELS: usize;
+
+    /// The max value of a new image made of these pixels.
+    const MAX_VAL: u16;
+
+    /// Makes a pixel from `CHANNELS` samples.
+    fn from_samples(samples: &[Self::Subpixel]) -> Self;
+
+    /// Copies the samples of the pixel into the first `CHANNELS` places of `out`.
+    fn to_samples(&self, out: &mut [Self::Subpixel]);
+}
+
+/// A black and white pixel as stored in pbm images, where `true` is black.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Bit(pub bool);
+
+/// A gray scale pixel.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Luma<T>(pub [T; 1]);
+
+/// A gray scale pixel with an alpha channel.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct LumaA<T>(pub [T; 2]);
+
+/// A color pixel with red, green, and blue samples.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Rgb<T>(pub [T; 3]);
+
+/// A color pixel with red, green, blue, and alpha samples.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Rgba<T>(pub [T; 4]);
+
+impl Pixel for Bit {
+    type Subpixel = u8;
+    const CHANNELS: usize = 1;
+    const MAX_VAL: u16 = 1;
+
+    fn from_samples(samples: &[u8]) -> Bit {
+        Bit(samples[0] != 0)
+    }
+
+    fn to_samples(&self, out: &mut [u8]) {
+        out[0] = self.0 as u8;
+    }
+}
+
+macro_rules! array_pixel {
+    ($name:ident, $channels:expr) => {
+        impl<T: Sample> Pixel for $name<T> {
+            type Subpixel = T;
+            const CHANNELS: usize = $channels;
+            const MAX_VAL: u16 = T::MAX_VAL;
+
+            fn from_samples(samples: &[T]) -> $name<T> {
+                let mut pixel = [T::default(); $channels];
+                pixel.copy_from_slice(&samples[..$channels]);
+                $name(pixel)
+            }
+
+            fn to_samples(&self, out: &mut [T]) {
+                out[..$channels].copy_from_slice(&self.0);
+            }
+        }
+    };
+}
+
+array_pixel!(Luma, 1);
+array_pixel!(LumaA, 2);
+array_pixel!(Rgb, 3);
+array_pixel!(Rgba, 4);
+
+/// An image made of pixels of type `P`.
+///
+/// Pixels are stored row by row starting at the top left corner. `x` counts pixels from the left
+/// and `y` counts rows from the top. The max value and comments of the image are kept so it can be
+/// saved again without losing them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImageBuffer<P: Pixel> {
+    width: u32,
+    height: u32,
+    pixels: Vec<P>,
+    max_val: u16,
+    comments: Vec<Comment>,
+}
+
+impl<P: Pixel> ImageBuffer<P> {
+    /// Create a new `ImageBuffer` with every sample set to 0.
+    ///
+    /// The max value is the largest that `P` can hold and there are no comments.
+    pub fn new(width: u32, height: u32) -> ImageBuffer<P> {
+        ImageBuffer{width, height, pixels: vec![P::default(); width as usize * height as usize],
+                    max_val: P::MAX_VAL, comments: vec![]}
+    }
+
+    /// Create a new `ImageBuffer` from a list of pixels stored row by row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DimensionMismatch` if there isn't exactly one pixel for each place in the image.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<P>) -> Result<ImageBuffer<P>, Error> {
+        let expected = width as usize * height as usize;
+        if pixels.len() != expected {
+            return Result::Err(Error::DimensionMismatch{expected, found: pixels.len()});
+        }
+        Ok(ImageBuffer{width, height, pixels, max_val: P::MAX_VAL, comments: vec![]})
+    }
+
+    /// Create a new `ImageBuffer` from the samples of an `Image` loaded by a decoder.
+    ///
+    /// The max value and comments of the image are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if the bit depth of the image doesn't match the samples of `P`
+    /// and `DimensionMismatch` if the image doesn't have `P::CHANNELS` samples for each pixel.
+    pub fn from_image(image: &Image) -> Result<ImageBuffer<P>, Error> {
+        let mut buffer = ImageBuffer::from_bytes(image.width, image.height, &image.dat, image.depth)?;
+        buffer.max_val = image.max_val;
+        buffer.comments = image.comments.clone();
+        Ok(buffer)
+    }
+
+    /// Create a new `ImageBuffer` from the samples of a `PAMImage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `from_image`, as well as `InvalidParameter` if the number of
+    /// channels in the image isn't `P::CHANNELS`.
+    pub fn from_pam_image(image: &PAMImage) -> Result<ImageBuffer<P>, Error> {
+        if image.channels as usize != P::CHANNELS {
+            return Result::Err(Error::InvalidParameter("The number of channels doesn't match the pixel type."));
+        }
+        let mut buffer = ImageBuffer::from_bytes(image.width, image.height, &image.dat, image.depth)?;
+        buffer.max_val = image.max_val;
+        buffer.comments = image.comments.clone();
+        Ok(buffer)
+    }
+
+    /// Create a new `ImageBuffer` from samples stored as bytes in the layout used by the files.
+    fn from_bytes(width: u32, height: u32, dat: &[u8], depth: BitDepth) -> Result<ImageBuffer<P>, Error> {
+        if depth != P::Subpixel::DEPTH {
+            return Result::Err(Error::InvalidParameter("The bit depth doesn't match the pixel type."));
+        }
+        let bytes = if depth == BitDepth::EIGHT { 1 } else { 2 };
+        let pixel_len = P::CHANNELS * bytes;
+        let expected = width as usize * height as usize * pixel_len;
+        if dat.len() != expected {
+            return Result::Err(Error::DimensionMismatch{expected, found: dat.len()});
+        }
+        let mut samples = vec![P::Subpixel::default(); P::CHANNELS];
+        let pixels = dat.chunks(pixel_len).map(|p| {
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample = P::Subpixel::from_bytes(&p[i * bytes..]);
+            }
+            P::from_samples(&samples)
+        }).collect();
+        Ok(ImageBuffer{width, height, pixels, max_val: P::MAX_VAL, comments: vec![]})
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The largest value a sample can have.
+    pub fn max_val(&self) -> u16 {
+        self.max_val
+    }
+
+    /// Sets the max value saved with the image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnsupportedMaxVal` if the max value is 0 or needs a different bit depth than the
+    /// samples of `P`. Bit images can only have a max value of 1.
+    pub fn set_max_val(&mut self, max_val: u16) -> Result<(), Error> {
+        let bits = P::MAX_VAL == 1;
+        if max_val == 0 || depth_for(max_val) != P::Subpixel::DEPTH || (bits && max_val != 1) {
+            return Result::Err(Error::UnsupportedMaxVal(max_val as u32));
+        }
+        self.max_val = max_val;
+        Ok(())
+    }
+
+    /// The comments saved with the image.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// Sets the comments saved with the image.
+    pub fn set_comments(&mut self, comments: &[Comment]) {
+        self.comments = comments.to_vec();
+    }
+
+    /// Gets the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the image.
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        self.pixels[self.index(x, y)]
+    }
+
+    /// Sets the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the image.
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        let i = self.index(x, y);
+        self.pixels[i] = pixel;
+    }
+
+    /// Finds where the pixel at `(x, y)` is stored.
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "Pixel ({}, {}) is outside of a {}x{} image.", x, y, self.width, self.height);
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Gets the pixels in row `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is not less than the height of the image.
+    pub fn row(&self, y: u32) -> &[P] {
+        assert!(y < self.height, "Row {} is outside of an image {} rows high.", y, self.height);
+        let start = y as usize * self.width as usize;
+        &self.pixels[start..start + self.width as usize]
+    }
+
+    /// Gets the pixels in row `y` so they can be changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is not less than the height of the image.
+    pub fn row_mut(&mut self, y: u32) -> &mut [P] {
+        assert!(y < self.height, "Row {} is outside of an image {} rows high.", y, self.height);
+        let start = y as usize * self.width as usize;
+        &mut self.pixels[start..start + self.width as usize]
+    }
+
+    /// Iterates over the rows of the image from top to bottom.
+    pub fn rows(&self) -> slice::Chunks<'_, P> {
+        self.pixels.chunks(self.width.max(1) as usize)
+    }
+
+    /// Iterates over every pixel, row by row.
+    pub fn pixels(&self) -> slice::Iter<'_, P> {
+        self.pixels.iter()
+    }
+
+    /// Iterates over every pixel, row by row, so they can be changed.
+    pub fn pixels_mut(&mut self) -> slice::IterMut<'_, P> {
+        self.pixels.iter_mut()
+    }
+
+    /// Iterates over every pixel along with its `x` and `y` coordinates.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (u32, u32, &P)> {
+        let width = self.width.max(1);
+        self.pixels.iter().enumerate().map(move |(i, p)| (i as u32 % width, i as u32 / width, p))
+    }
+
+    /// Gets all of the pixels, row by row.
+    pub fn as_pixels(&self) -> &[P] {
+        &self.pixels
+    }
+
+    /// Converts the image to bytes in the layout used by the encoders.
+    ///
+    /// The result can be passed straight to `save_max_val` along with `max_val`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bytes = if P::Subpixel::DEPTH == BitDepth::EIGHT { 1 } else { 2 };
+        let mut out:Vec<u8> = Vec::with_capacity(self.pixels.len() * P::CHANNELS * bytes);
+        let mut samples = vec![P::Subpixel::default(); P::CHANNELS];
+        for pixel in &self.pixels {
+            pixel.to_samples(&mut samples);
+            for sample in &samples {
+                sample.push_bytes(&mut out);
+            }
+        }
+        out
+    }
+
+    /// Converts the image to an `Image`, keeping the max value and comments.
+    pub fn to_image(&self) -> Image {
+        Image{width: self.width, height: self.height, dat: self.to_bytes(), depth: P::Subpixel::DEPTH,
+              max_val: self.max_val, comments: self.comments.clone()}
+    }
+}
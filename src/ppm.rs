@@ -1,22 +1,38 @@
-use std::io;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
-use tools::{get_header, ImageType};
+use std::io::Seek;
+use tools::{check_buffer, check_len, check_region, check_stride, depth_for, encoder_row_len, full_max_val, load_raster, load_raster_into, load_raster_into_u16, load_raster_lenient, load_raster_partial, load_region, rescale, samples_from_u16, view_raster, ImageHeader, Stream};
+use rows::{write_image, RowWriter, Rows};
+use Error;
+use ImageType;
+use Limits;
 use Mode;
 use Image;
+use ImageView;
+use LenientImage;
+use PartialImage;
+use Image16;
 use BitDepth;
+use Comment;
 
 /// Encodes an image as a ppm file.
-pub struct PPMEncoder {
-    f: File,
+///
+/// The encoder can write to anything that implements `Write`, such as a file, a socket, or a
+/// `Vec<u8>` in memory. `new` and `append` are provided as shortcuts for writing to files.
+pub struct PPMEncoder<W: Write = File> {
+    f: W,
+    comments: Vec<Comment>,
 }
 
-/// Decodes an image in the pgm format.
-pub struct PPMDecoder {
-    f: File,
+/// Decodes an image in the ppm format.
+///
+/// The decoder can read from anything that implements `Read`, such as a file, a pipe, or a byte
+/// slice in memory. `new` is provided as a shortcut for reading from files.
+pub struct PPMDecoder<R: Read = File> {
+    stream: Stream<R>,
 }
 
-impl PPMEncoder {
+impl PPMEncoder<File> {
     /// Create a new `PPMEncoder`
     ///
     /// Creates a new `PPMEncoder` by creating a file with the specified name. The file extension
@@ -28,12 +44,99 @@ impl PPMEncoder {
     ///  # use std::fs;
     /// use netbpm::ppm::PPMEncoder;
     ///
-    /// let encoder = PPMEncoder::new("ppm_file.ppm");
+    /// let encoder = PPMEncoder::new("ppm_file.ppm").unwrap();
     /// # let _ = fs::remove_file("ppm_file.ppm");
     /// ```
-    pub fn new(file_name: &str) -> PPMEncoder {
-        let file = File::create(file_name).unwrap();
-        PPMEncoder{f : file}
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be created.
+    pub fn new(file_name: &str) -> Result<PPMEncoder, Error> {
+        let file = File::create(file_name)?;
+        Ok(PPMEncoder{f : file, comments: vec![]})
+    }
+
+    /// Create a `PPMEncoder` that adds images to the end of a file.
+    ///
+    /// The standard allows several images to be stored one after another in the same file. Each
+    /// call to `save` writes a complete image, so an encoder made with this method can be used to
+    /// add frames to an existing stream. The file is created if it doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::ppm::{PPMEncoder,PPMDecoder};
+    /// use netbpm::{Mode,BitDepth};
+    ///
+    /// let frame:[u8;6] = [0,0,0, 255,255,255];
+    /// let mut encoder = PPMEncoder::new("frames.ppm").unwrap();
+    /// encoder.save(&frame, 2, 1, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    ///
+    /// let mut encoder = PPMEncoder::append("frames.ppm").unwrap();
+    /// encoder.save(&frame, 2, 1, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    ///
+    /// let decoder = PPMDecoder::new("frames.ppm").unwrap();
+    /// assert_eq!(decoder.count(), 2);
+    /// # let _ = fs::remove_file("frames.ppm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened or created.
+    pub fn append(file_name: &str) -> Result<PPMEncoder, Error> {
+        let file = OpenOptions::new().append(true).create(true).open(file_name)?;
+        Ok(PPMEncoder{f : file, comments: vec![]})
+    }
+}
+
+impl<W: Write> PPMEncoder<W> {
+    /// Create a new `PPMEncoder` that writes to any `Write`.
+    ///
+    /// Each call to `save` writes a complete image, so several images can be written to the same
+    /// stream one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMEncoder;
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let encoder = PPMEncoder::from_writer(&mut buffer);
+    /// ```
+    pub fn from_writer(writer: W) -> PPMEncoder<W> {
+        PPMEncoder{f : writer, comments: vec![]}
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.f
+    }
+
+    /// Sets the comments written in the header of every image saved after this.
+    ///
+    /// Each comment is written on its own line at its position in the header. The comments stay
+    /// set until this is called again, so pass an empty slice to stop writing them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMEncoder;
+    /// use netbpm::{BitDepth,Comment,Mode};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    /// encoder.set_comments(&[Comment{text: "camera 2".to_string(), position: 0}]);
+    /// encoder.save(&[255,0,0], 1, 1, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    /// ```
+    pub fn set_comments(&mut self, comments: &[Comment]) {
+        self.comments = comments.to_vec();
+    }
+
+    /// Builds the header for an image, including the comments set on the encoder.
+    fn header(&self, width: u32, height: u32, mode: Mode, max_val: u16) -> ImageHeader {
+        ImageHeader{width, height, dat_start: 0, image_type: ImageType::PPM, depth: depth_for(max_val), max_val,
+                    mode, channels: 3, tuple_type: String::new(), comments: self.comments.clone()}
     }
 
     /// Saves image data to the file stored by the `PPMEncoder`.
@@ -51,105 +154,193 @@ impl PPMEncoder {
     /// use netbpm::{Mode,BitDepth};
     ///
     /// // This will save an image of a `J` in an eight-bit, ascii ppm file
-    /// const data:[u8;180] = [255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        0,0,0,         255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
-    ///                        255,255,255,   0,0,0,         0,0,0,         0,0,0,         255,255,255,  255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   255,255,255,  255,255,255,
-    ///                        255,255,255,   255,255,255,   255,255,255,   255,255,255,   255,255,255,  255,255,255];
-    ///
-    /// let mut encoder = PPMEncoder::new("test_file.ppm");
+    /// const data:[u8;180] = [255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        0,0,0,        255,255,255,  255,255,255,
+    ///                        255,255,255,  0,0,0,        255,255,255,
+    ///                        255,255,255,  0,0,0,        0,0,0,
+    ///                        0,0,0,        255,255,255,  255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255,
+    ///                        255,255,255,  255,255,255,  255,255,255];
+    ///
+    /// let mut encoder = PPMEncoder::new("test_file.ppm").unwrap();
     /// encoder.save(&data, 6, 10, Mode::ASCII, BitDepth::EIGHT).unwrap();
     /// # let _ = fs::remove_file("test_file.ppm");
     /// ```
     ///
     /// # Errors
     ///
-    /// Aside from the regular errors associated with file access this function will throw an
-    /// error if the user attempts to save an image more than 70 characters wide in ASCII mode.
-    /// Note that because it is characters and not pixels you will frequently only be able to save
-    /// fewer than 70 pixels, especially in 16-bit mode.
-    pub fn save(&mut self, dat: &[u8], height: u32, width: u32, mode: Mode, depth: BitDepth) -> Result<(), io::Error> {
-        Ok(
-            match mode {
-                Mode::ASCII => try!(self.save_ascii(dat, height, width, depth)),
-                Mode::BINARY => try!(self.save_binary(dat, height, width, depth)),
-            }
-        )
+    /// Returns `DimensionMismatch` if `dat` doesn't hold exactly one sample for each channel of
+    /// each pixel of the image, in one or two bytes depending on the bit depth. Any error from
+    /// writing to the underlying writer is also returned.
+    pub fn save(&mut self, dat: &[u8], width: u32, height: u32, mode: Mode, depth: BitDepth) -> Result<(), Error> {
+        let max_val = full_max_val(&depth);
+        self.save_max_val(dat, width, height, mode, max_val)
     }
 
-    /// Saves a ppm file in ascii format.
+    /// Saves image data with an arbitrary max value.
     ///
-    /// This method saves the data in ascii format. This is a bit tricky as the ints needs to be
-    /// converted to strings while at the same time keeping track of the total number of characters
-    /// on the line to prevent more than 70 being saved.
+    /// This works the same as `save` except that the max value written to the header can be any
+    /// value from 1 to 65535 instead of only 255 or 65535. Samples are stored in one byte if the
+    /// max value is less than 256 and in two bytes, most significant byte first, otherwise.
     ///
-    /// TODO: Double-check that we're not missing a default API call which would make this easier.
-    fn save_ascii(&mut self, dat: &[u8], width: u32, height: u32, depth: BitDepth) -> Result<(), io::Error> {
-        // In theory we can ignore this with no downside but it would no longer be conformant.
-        if width > 70 {
-            return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Width can not be greater than 70 for ascii pgm files."));
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// use netbpm::ppm::PPMEncoder;
+    /// use netbpm::Mode;
+    ///
+    /// // A 12-bit image stores each sample in two bytes.
+    /// const data:[u8;12] = [0,0, 0,0, 0,0,   15,255, 15,255, 15,255];
+    ///
+    /// let mut encoder = PPMEncoder::new("test_file_12_bit.ppm").unwrap();
+    /// encoder.save_max_val(&data, 2, 1, Mode::BINARY, 4095).unwrap();
+    /// # let _ = fs::remove_file("test_file_12_bit.ppm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by `save` this will return an error if the max value
+    /// is 0, and `SampleOutOfRange` if a sample is greater than the max value. Nothing is written
+    /// if any sample is out of range.
+    pub fn save_max_val(&mut self, dat: &[u8], width: u32, height: u32, mode: Mode, max_val: u16) -> Result<(), Error> {
+        if max_val == 0 {
+            return Result::Err(Error::UnsupportedMaxVal(0));
         }
-        // write the header information.
-        try!(self.f.write_fmt(format_args!("P3\n{} {}\n",width,height)));
-        match depth {
-            BitDepth::EIGHT => try!(self.f.write_all(b"255\n")),
-            BitDepth::SIXTEEN => try!(self.f.write_all(b"65535\n")),
+        let row_len = encoder_row_len(width, 3, max_val)?;
+        check_len(dat, row_len, height)?;
+        let header = self.header(width, height, mode, max_val);
+        write_image(&mut self.f, header, dat, row_len)
+    }
+
+    /// Saves image data whose rows are spaced out in a larger buffer.
+    ///
+    /// This works the same as `save_max_val` except that each row starts `stride` bytes after the
+    /// start of the row before it, rather than straight after it. Any bytes between the rows are
+    /// skipped. This saves part of a larger image, or an image with padded rows, without copying
+    /// it first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMEncoder;
+    /// use netbpm::Mode;
+    ///
+    /// // Each row is followed by three bytes of padding.
+    /// const data:[u8;9] = [255,0,0, 9,9,9, 0,0,255];
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    /// encoder.save_stride(&data, 1, 2, 6, Mode::BINARY, 255).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if `stride` is less than the length of a row and
+    /// `DimensionMismatch` if `dat` ends before the end of the last row. Otherwise this returns the
+    /// same errors as `save_max_val`.
+    pub fn save_stride(&mut self, dat: &[u8], width: u32, height: u32, stride: usize, mode: Mode, max_val: u16) -> Result<(), Error> {
+        if max_val == 0 {
+            return Result::Err(Error::UnsupportedMaxVal(0));
         }
+        let row_len = encoder_row_len(width, 3, max_val)?;
+        check_stride(dat, row_len, height, stride)?;
+        let header = self.header(width, height, mode, max_val);
+        write_image(&mut self.f, header, dat, stride)
+    }
 
-        // track if we need to insert a new line character at the end of a row.
-        //let mut nl = 1;
-        // track the number of characters in the line.
-        let mut counter = 0;
-        // write the actual image data.
-        for i in 0..height {
-            for j in 0..(width * 3) {
-                let val:u16 = match depth {
-                    BitDepth::EIGHT => dat[((i * (width * 3)) + j) as usize] as u16,
-                    BitDepth::SIXTEEN => {
-                        let ind:usize = (((i * (width * 3)) + j) * 2) as usize;
-                        ((dat[ind] as u16) << 8) + dat[ind] as u16
-                    },
-                };
-                let v = val.to_string();
-                counter = counter + v.len();
-                if counter > 70 {
-                    return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Width can not be greater than 70 characters for ascii ppm files."));
-                }
-                try!(self.f.write_all(&v.into_bytes()));
-                if j < (width * 3)-1 {
-                    try!(self.f.write_all(b" "));
-                }
-            }
-            counter = 0;
-            try!(self.f.write_all(b"\n"));
+    /// Saves image data given as `u16` samples.
+    ///
+    /// This works the same as `save_max_val` except that each sample is a `u16` rather than one
+    /// or two bytes. The samples are converted to the byte layout used by the file, so there is
+    /// no need to split them into pairs of bytes first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMEncoder;
+    /// use netbpm::Mode;
+    ///
+    /// const data:[u16;6] = [4095,0,0, 0,0,4095];
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    /// encoder.save_u16(&data, 2, 1, Mode::BINARY, 4095).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by `save_max_val` this will return an error if any
+    /// sample is greater than the max value.
+    pub fn save_u16(&mut self, dat: &[u16], width: u32, height: u32, mode: Mode, max_val: u16) -> Result<(), Error> {
+        if max_val == 0 {
+            return Result::Err(Error::UnsupportedMaxVal(0));
         }
+        let bytes = samples_from_u16(dat, max_val)?;
+        self.save_max_val(&bytes, width, height, mode, max_val)
+    }
 
-        Ok(())
+    /// Starts saving an image one row at a time.
+    ///
+    /// The header is written straight away from the width, height, `Mode`, and `BitDepth`. Each row
+    /// is then written with `write_row` on the returned `RowWriter`, so the whole image never needs
+    /// to be in memory at once. Long rows in ASCII mode are split over several lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMEncoder;
+    /// use netbpm::{Mode,BitDepth};
+    ///
+    /// let mut buffer:Vec<u8> = vec![];
+    /// let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    /// let mut writer = encoder.rows(2, 2, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    /// writer.write_row(&[255,0,0, 0,255,0]).unwrap();
+    /// writer.write_row(&[0,0,255, 0,0,0]).unwrap();
+    /// writer.finish().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the header.
+    pub fn rows(&mut self, width: u32, height: u32, mode: Mode, depth: BitDepth) -> Result<RowWriter<'_, W>, Error> {
+        let max_val = full_max_val(&depth);
+        self.rows_max_val(width, height, mode, max_val)
     }
 
-    /// Save a ppm file in binary format.
+    /// Starts saving an image with an arbitrary max value one row at a time.
+    ///
+    /// This works the same as `rows` except that the max value can be anything from 1 to 65535, as
+    /// with `save_max_val`.
+    ///
+    /// # Errors
     ///
-    /// Saving in binary is much easier than in ascii as, after we construct the header, we can
-    /// just write the input data directly.
-    fn save_binary(&mut self, dat: &[u8], width: u32, height: u32, depth: BitDepth) -> Result<(), io::Error> {
-        // write the header
-        try!(self.f.write_fmt(format_args!("P6\n{} {}\n",width,height)));
-        match depth {
-            BitDepth::EIGHT => try!(self.f.write_all(b"255\n")),
-            BitDepth::SIXTEEN => try!(self.f.write_all(b"65535\n")),
+    /// In addition to the errors returned by `rows` this will return an error if the max value is
+    /// 0.
+    pub fn rows_max_val(&mut self, width: u32, height: u32, mode: Mode, max_val: u16) -> Result<RowWriter<'_, W>, Error> {
+        if max_val == 0 {
+            return Result::Err(Error::UnsupportedMaxVal(0));
         }
-        // write the image data
-        try!(self.f.write_all(dat));
-        Ok(())
+        let header = self.header(width, height, mode, max_val);
+        RowWriter::new(&mut self.f, header)
     }
+
 }
 
-impl PPMDecoder {
+impl PPMDecoder<File> {
     /// Create a new `PPMDecoder`
     ///
     /// Creates a new `PPMDecoder` that reads from the specified file. The file extension does not
@@ -163,12 +354,44 @@ impl PPMDecoder {
     /// use netbpm::ppm::PPMDecoder;
     ///
     /// # let _ = File::create("saved_file.ppm");
-    /// let decoder = PPMDecoder::new("saved_file.ppm");
+    /// let decoder = PPMDecoder::new("saved_file.ppm").unwrap();
     ///  # let _ = fs::remove_file("saved_file.ppm");
     /// ```
-    pub fn new(file_name: &str) -> PPMDecoder {
-        let file = File::open(file_name).unwrap();
-        PPMDecoder{f : file}
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` if the file can't be opened.
+    pub fn new(file_name: &str) -> Result<PPMDecoder, Error> {
+        let file = File::open(file_name)?;
+        Ok(PPMDecoder{stream : Stream::new(file)})
+    }
+}
+
+impl<R: Read> PPMDecoder<R> {
+    /// Create a new `PPMDecoder` that reads from any `Read`.
+    ///
+    /// Only as much data as is needed is read for each image, so images can be loaded from a pipe
+    /// or socket as they arrive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P3\n1 2\n255\n0 0 0\n255 255 255\n";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// let image = decoder.load().unwrap();
+    /// ```
+    pub fn from_reader(reader: R) -> PPMDecoder<R> {
+        PPMDecoder{stream : Stream::new(reader)}
+    }
+
+    /// Sets the limits on the size of images loaded after this.
+    ///
+    /// Images larger than the limits are rejected with `LimitExceeded` as soon as their header is
+    /// read. See `Limits` for details.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.stream.set_limits(limits);
     }
 
     /// Loads a ppm file.
@@ -176,6 +399,9 @@ impl PPMDecoder {
     /// Will load a ppm file that's in either ASCII or binary format. The file extension does not
     /// matter.
     ///
+    /// If the file holds more than one image this loads the next one, so calling it again will
+    /// load the image after it. The decoder can also be used as an `Iterator` over the images.
+    ///
     /// # Examples
     ///
     /// ```
@@ -187,7 +413,7 @@ impl PPMDecoder {
     ///
     /// # let mut file = File::create("image.ppm").unwrap();
     /// # let _ = file.write(b"P3\n2 2\n255\n0 0 0 255 255 255\n255 255 255 0 0 0");
-    /// let mut decoder = PPMDecoder::new("image.ppm");
+    /// let mut decoder = PPMDecoder::new("image.ppm").unwrap();
     /// let image = decoder.load().unwrap();
     /// # let _ = fs::remove_file("image.ppm");
     /// ```
@@ -195,47 +421,327 @@ impl PPMDecoder {
     /// # Errors
     ///
     /// This method will return all general file IO errors that can be raised by file read
-    /// operations. Additionally, it will return an error if the image type is not ppm as well
-    /// as all file header parsing errors.
-    pub fn load(&mut self) ->  Result<Image, io::Error> {
-        let mut all_data:Vec<u8> = vec![];
-        try!(self.f.read_to_end(&mut all_data));
-        let header = try!(get_header(&all_data));
+    /// operations. Additionally, it will return an error if the image type is not ppm, if there are
+    /// no images left in the file, as well as all file header parsing errors. A `Truncated` error
+    /// is returned if the file ends before the end of the raster, an `UnexpectedRasterCharacter`
+    /// error if an ASCII raster holds anything other than samples, whitespace, and comments, and a
+    /// `SampleOutOfRange` error if a sample is greater than the max value.
+    pub fn load(&mut self) ->  Result<Image, Error> {
+        let header = self.next_header()?;
+        let dat = load_raster(&mut self.stream, &header)?;
+
+        Ok(Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                 comments: header.comments})
+    }
+
+    /// Loads a ppm file even if it ends part way through the raster.
+    ///
+    /// This works the same as `load` except that a raster cut short doesn't cause an error.
+    /// Instead the missing samples are set to 0 and the returned `PartialImage` records how many
+    /// rows were read in full. This is useful for showing as much as possible of a file that is
+    /// still being downloaded or was damaged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P3\n1 2\n255\n255 0 0\n0 0";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// let partial = decoder.load_partial().unwrap();
+    /// assert!(!partial.complete);
+    /// assert_eq!(partial.rows, 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for `Truncated` errors in the raster.
+    pub fn load_partial(&mut self) -> Result<PartialImage<Image>, Error> {
+        let header = self.next_header()?;
+        let (dat, rows) = load_raster_partial(&mut self.stream, &header)?;
+        let complete = rows == header.height;
+
+        let image = Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                          comments: header.comments};
+        Ok(PartialImage{image, rows, complete})
+    }
+
+    /// Loads a ppm file, working around problems that `load` would return as errors.
+    ///
+    /// Characters other than samples, whitespace, and comments in an ASCII raster are skipped,
+    /// samples greater than the max value are lowered to it, a raster that ends early is filled
+    /// with zeros, and anything after the raster that isn't another image is skipped. Each kind of
+    /// problem found is reported as a `Warning` alongside the image, so damaged files can be loaded
+    /// without the damage going unnoticed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    /// use netbpm::Warning;
+    ///
+    /// let data:&[u8] = b"P3\n1 1\n100\n50 x 120 0\n";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// let lenient = decoder.load_lenient().unwrap();
+    /// assert_eq!(lenient.image.dat, vec![50, 100, 0]);
+    /// assert_eq!(lenient.warnings[0], Warning::UnexpectedCharacters{count: 1, offset: 3});
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`, except for errors in the raster.
+    pub fn load_lenient(&mut self) -> Result<LenientImage<Image>, Error> {
+        let header = self.next_header()?;
+        let (dat, warnings) = load_raster_lenient(&mut self.stream, &header)?;
+
+        let image = Image{width:header.width, height:header.height, dat, depth: header.depth, max_val: header.max_val,
+                          comments: header.comments};
+        Ok(LenientImage{image, warnings})
+    }
+
+    /// Loads a ppm file with each sample stored as a `u16`.
+    ///
+    /// This loads the file the same way as `load` and then converts the samples to `u16`s, so
+    /// sixteen-bit samples don't need to be put together from pairs of bytes. Eight-bit samples
+    /// are widened without being scaled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P3\n1 1\n4095\n4095 0 0\n";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// let image = decoder.load_u16().unwrap();
+    /// assert_eq!(image.dat[0], 4095);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`.
+    pub fn load_u16(&mut self) -> Result<Image16, Error> {
+        Ok(Image16::from(self.load()?))
+    }
+
+    /// Loads a ppm file into a buffer provided by the caller.
+    ///
+    /// This works the same as `load` except that the samples are written to `buf` instead of a new
+    /// `Vec`, so one buffer can be reused for every image in a stream. `buf` must be exactly the
+    /// size of the image data, in the same layout as `Image::dat`. The header of the image is
+    /// returned so its size and max value are known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P6\n1 1\n255\n\x01\x02\x03P6\n1 1\n255\n\x04\x05\x06";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// let mut frame = [0u8; 3];
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [1, 2, 3]);
+    /// decoder.decode_into(&mut frame).unwrap();
+    /// assert_eq!(frame, [4, 5, 6]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`. A `DimensionMismatch` error is returned if `buf`
+    /// isn't the size of the image, in which case nothing is read and the image can be loaded
+    /// again with a buffer of the right size.
+    pub fn decode_into(&mut self, buf: &mut [u8]) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        check_buffer(&header, buf)?;
+        self.stream.skip_header(&header);
+        load_raster_into(&mut self.stream, &header, buf)?;
+        Ok(header)
+    }
+
+    /// Loads a ppm file into a buffer of `u16`s provided by the caller.
+    ///
+    /// This works the same as `decode_into` except that each sample takes up one place in `buf`,
+    /// the same as in `Image16::dat`. Eight-bit samples are widened without being scaled.
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `decode_into`.
+    pub fn decode_into_u16(&mut self, buf: &mut [u16]) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        check_buffer(&header, buf)?;
+        self.stream.skip_header(&header);
+        load_raster_into_u16(&mut self.stream, &header, buf)?;
+        Ok(header)
+    }
+
+    /// Reads the header of the next image, checking that it is a ppm image within the limits.
+    fn next_header(&mut self) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.stream.check_size(&header)?;
+        self.stream.skip_header(&header);
+        Ok(header)
+    }
+
+    /// Reads the header of the next image without moving past it, checking that it is a ppm
+    /// image. The size of the image isn't checked against the limits.
+    fn peek_header(&mut self) -> Result<ImageHeader, Error> {
+        if self.stream.at_end()? {
+            return Result::Err(Error::NoMoreImages);
+        }
+        let header = self.stream.peek_header()?;
 
         // check the magic number.
         if header.image_type != ImageType::PPM {
-            return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Input file is not a ppm file."));
+            return Result::Err(Error::BadMagicNumber{expected: Some("ppm")});
         }
+        Ok(header)
+    }
 
-        match header.mode {
-            Mode::ASCII => Ok(Image{width:header.width, height:header.height,
-                           dat:self.load_ascii(&all_data[header.dat_start..all_data.len()], &header.depth),
-                           depth: header.depth}),
-            // unlike with the pbm file format we can take raw binary data with no processing.
-            Mode::BINARY => Ok(Image{width:header.width, height:header.height,
-                           dat:all_data[header.dat_start..all_data.len()].to_vec(),
-                           depth: header.depth})
-        }
+    /// Loads a ppm file and rescales it to the full range of its bit depth.
+    ///
+    /// Files can use any max value from 1 to 65535. This loads the file the same way as `load`
+    /// and then scales every sample so that the max value becomes 255 for eight-bit images or
+    /// 65535 for sixteen-bit images. The `max_val` of the returned image is set to match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use std::fs::File;
+    /// # use std::io::prelude::*;
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// # let mut file = File::create("image_rescaled.ppm").unwrap();
+    /// # let _ = file.write(b"P3\n1 2\n15\n0 0 0\n15 15 15\n");
+    /// let mut decoder = PPMDecoder::new("image_rescaled.ppm").unwrap();
+    /// let image = decoder.load_rescaled().unwrap();
+    /// assert_eq!(image.max_val, 255);
+    /// # let _ = fs::remove_file("image_rescaled.ppm");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`.
+    pub fn load_rescaled(&mut self) -> Result<Image, Error> {
+        let mut image = self.load()?;
+        rescale(&mut image.dat, &image.depth, image.max_val);
+        image.max_val = full_max_val(&image.depth);
+        Ok(image)
+    }
+
+    /// Starts loading the next image one row at a time.
+    ///
+    /// The header is read straight away and the raster is read as each row is requested, so only
+    /// one row of the image needs to be in memory at once. See the `rows` module for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P3\n2 1\n255\n255 0 0  0 0 255\n";
+    /// let mut decoder = PPMDecoder::from_reader(data);
+    /// for row in decoder.rows().unwrap() {
+    ///     let row = row.unwrap();
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same header errors as `load`. Errors in the raster are returned when the
+    /// row is read.
+    pub fn rows(&mut self) -> Result<Rows<'_, R>, Error> {
+        let header = self.next_header()?;
+        Ok(Rows::new(&mut self.stream, header))
+    }
+}
 
+impl<R: Read + Seek> PPMDecoder<R> {
+    /// Loads a rectangle from a binary ppm file without reading the rest of the raster.
+    ///
+    /// The region is `w` by `h` pixels with its top left corner at `x`, `y`. Every row of a binary
+    /// raster takes up the same number of bytes, so the decoder seeks straight to each row of the
+    /// region and reads only the samples inside it. This makes it possible to take small crops out
+    /// of very large files. Afterwards the decoder is left at the end of the image, so the next
+    /// image in the file can be loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use netbpm::ppm::PPMDecoder;
+    ///
+    /// let data:&[u8] = b"P6\n2 2\n255\n\x00\x00\x00\x01\x01\x01\x02\x02\x02\x03\x03\x03";
+    /// let mut decoder = PPMDecoder::from_reader(Cursor::new(data));
+    /// let region = decoder.decode_region(1, 0, 1, 2).unwrap();
+    /// assert_eq!(region.dat, vec![1, 1, 1, 3, 3, 3]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This returns the same errors as `load`. Additionally, it will return `InvalidParameter` if
+    /// the image is stored in ASCII or the region isn't inside the image. The limits set on the
+    /// decoder are checked against the size of the region rather than the whole image.
+    pub fn decode_region(&mut self, x: u32, y: u32, w: u32, h: u32) -> Result<Image, Error> {
+        let header = self.peek_header()?;
+        check_region(&header, self.stream.limits(), x, y, w, h)?;
+        self.stream.skip_header(&header);
+        let dat = load_region(&mut self.stream, &header, x, y, w, h)?;
+
+        Ok(Image{width: w, height: h, dat, depth: header.depth, max_val: header.max_val, comments: header.comments})
     }
+}
+
+impl<R: Read> Iterator for PPMDecoder<R> {
+    type Item = Result<Image, Error>;
 
-    /// Load image data stored in ASCII format.
-    fn load_ascii(&self, inpt: &[u8], depth: &BitDepth) -> Vec<u8> {
-        let mut vals:Vec<u8> = vec![];
-        let mut num:u16 = 0;
-        for x in inpt{
-            if *x > 47 && *x < 58 {
-                num = (num * 10) + (*x - 48) as u16;
-            } else {
-                if *depth == BitDepth::EIGHT {
-                    vals.push(num as u8);
-                } else {
-                    vals.push((num >> 8) as u8);  // most significant byte first.
-                    vals.push((num & 255) as u8); // least significant byte second.
+    /// Loads the next image in the file.
+    ///
+    /// A file can hold several ppm images one after another. Each call loads the next one until
+    /// the end of the file is reached. If an image can't be loaded the error is returned and no
+    /// more images will be read.
+    fn next(&mut self) -> Option<Result<Image, Error>> {
+        match self.stream.at_end() {
+            Ok(true) => None,
+            Ok(false) => {
+                let image = self.load();
+                if image.is_err() {
+                    self.stream.finish();
                 }
-                num = 0;
-            }
+                Some(image)
+            },
+            Err(e) => {
+                self.stream.finish();
+                Some(Result::Err(Error::Io(e)))
+            },
         }
-        vals
     }
 }
+
+/// Makes a view of a binary ppm image stored in memory, without copying the raster.
+///
+/// `dat` holds the whole file, header and all. The returned `ImageView` points straight at the
+/// raster inside `dat`, which makes this much faster than `load` when many small images are read
+/// and only looked at once. To view a file without reading it into memory at all, map it with the
+/// memory mapping of the operating system and pass the mapped bytes. If `dat` holds more than one
+/// image only the first is viewed.
+///
+/// # Examples
+///
+/// ```
+/// let file:&[u8] = b"P6\n1 2\n255\n\xff\x00\x00\x00\x00\xff";
+/// let view = netbpm::ppm::view(file).unwrap();
+/// assert_eq!(view.height, 2);
+/// assert_eq!(&view.dat[3..], &[0, 0, 255]);
+/// ```
+///
+/// # Errors
+///
+/// Returns `BadMagicNumber` if the image isn't a ppm image, `InvalidParameter` if it is
+/// stored in ASCII, `Truncated` if `dat` ends before the end of the raster, and
+/// `SampleOutOfRange` if a sample is greater than the max value, as well as all header parsing
+/// errors.
+pub fn view(dat: &[u8]) -> Result<ImageView<'_>, Error> {
+    view_raster(dat, ImageType::PPM, "ppm")
+}
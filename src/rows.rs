@@ -0,0 +1,290 @@
+//! Provides row-by-row loading and saving for images that are too large to hold in memory at once.
+//!
+//! Each decoder has a `rows` method that reads the header of the next image and returns a
+//! `Rows`. The raster is then read one row at a time, either into a buffer provided by the caller
+//! with `read_row` or as a new `Vec<u8>` for each row by using `Rows` as an `Iterator`. Only one
+//! row of the image is held in memory by the decoder at any time.
+//!
+//! Each encoder has a matching `rows` method that writes the header and returns a `RowWriter`.
+//! Rows are then written one at a time with `write_row` and `finish` checks that the whole image
+//! was written.
+//!
+//! Rows hold samples in the same layout as `Image::dat`. Pbm rows have one byte per pixel, and
+//! sixteen-bit samples are two bytes with the most significant byte first.
+//!
+//! # Examples
+//!
+//! ```
+//! use netbpm::pgm::{PGMEncoder,PGMDecoder};
+//! use netbpm::{Mode,BitDepth};
+//!
+//! let mut buffer:Vec<u8> = vec![];
+//! {
+//!     let mut encoder = PGMEncoder::from_writer(&mut buffer);
+//!     let mut writer = encoder.rows(3, 2, Mode::ASCII, BitDepth::EIGHT).unwrap();
+//!     writer.write_row(&[0, 128, 255]).unwrap();
+//!     writer.write_row(&[255, 128, 0]).unwrap();
+//!     writer.finish().unwrap();
+//! }
+//!
+//! let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+//! let mut rows = decoder.rows().unwrap();
+//! let mut row = vec![0; rows.row_len()];
+//! while rows.read_row(&mut row).unwrap() {
+//!     // use the row.
+//! }
+//! ```
+
+use std::io::prelude::*;
+use tools::*;
+use BitDepth;
+use Comment;
+use Error;
+use ImageType;
+use Mode;
+
+/// The longest line allowed in the raster of an ASCII image.
+const MAX_LINE: usize = 70;
+
+/// Reads the raster of one image a row at a time.
+///
+/// The stream is left at the end of the image once every row has been read, so the decoder can go
+/// on to load the next image. If a `Rows` is dropped before the last row the decoder is left part
+/// way through the raster and any later images can't be loaded.
+pub struct Rows<'a, R: Read + 'a> {
+    stream: &'a mut Stream<R>,
+    header: ImageHeader,
+    row: u32,
+    check: RasterCheck,
+}
+
+impl<'a, R: Read> Rows<'a, R> {
+    pub(crate) fn new(stream: &'a mut Stream<R>, header: ImageHeader) -> Rows<'a, R> {
+        Rows{stream, header, row: 0, check: RasterCheck::strict()}
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.header.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+
+    /// The number of samples in each pixel.
+    pub fn channels(&self) -> u32 {
+        self.header.channels
+    }
+
+    /// The number of bytes used by each sample.
+    pub fn depth(&self) -> BitDepth {
+        self.header.depth
+    }
+
+    /// The largest value a sample can have. For pbm images this is always 1.
+    pub fn max_val(&self) -> u16 {
+        self.header.max_val
+    }
+
+    /// Whether the image is stored in ASCII or binary format.
+    pub fn mode(&self) -> Mode {
+        self.header.mode
+    }
+
+    /// The comments from the header of the image.
+    pub fn comments(&self) -> &[Comment] {
+        &self.header.comments
+    }
+
+    /// The whole header of the image.
+    pub fn header(&self) -> &ImageHeader {
+        &self.header
+    }
+
+    /// The number of bytes in each row.
+    pub fn row_len(&self) -> usize {
+        loaded_row_len(&self.header)
+    }
+
+    /// Reads the next row of the image into `row`.
+    ///
+    /// Returns `false` without changing `row` once every row has been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DimensionMismatch` if `row` is not `row_len` bytes long, `Truncated` if the data
+    /// ends part way through the row, and `SampleOutOfRange` if a sample is greater than the max
+    /// value. IO errors from the source are also returned.
+    pub fn read_row(&mut self, row: &mut [u8]) -> Result<bool, Error> {
+        if row.len() != self.row_len() {
+            return Result::Err(Error::DimensionMismatch{expected: self.row_len(), found: row.len()});
+        }
+        if self.row >= self.header.height {
+            return Ok(false);
+        }
+        if fill_raster(self.stream, &self.header, row, &mut self.check)? < row.len() {
+            return Result::Err(Error::Truncated);
+        }
+        self.row += 1;
+        Ok(true)
+    }
+}
+
+impl<'a, R: Read> Iterator for Rows<'a, R> {
+    type Item = Result<Vec<u8>, Error>;
+
+    /// Reads the next row of the image.
+    ///
+    /// If a row can't be read the error is returned and no more rows will be read.
+    fn next(&mut self) -> Option<Result<Vec<u8>, Error>> {
+        if self.row >= self.header.height {
+            return None;
+        }
+        let mut row = vec![0; self.row_len()];
+        match self.read_row(&mut row) {
+            Ok(true) => Some(Ok(row)),
+            Ok(false) => None,
+            Err(e) => {
+                self.row = self.header.height;
+                Some(Result::Err(e))
+            },
+        }
+    }
+}
+
+/// Writes the raster of one image a row at a time.
+///
+/// The header has already been written when a `RowWriter` is created. Every row of the image must
+/// then be written with `write_row` before calling `finish`. If a `RowWriter` is dropped without
+/// calling `finish` the image may be incomplete and no error is reported.
+pub struct RowWriter<'a, W: Write + 'a> {
+    f: &'a mut W,
+    header: ImageHeader,
+    row: u32,
+    // Packed bits of the current row for binary pbm images.
+    packed: Vec<u8>,
+}
+
+impl<'a, W: Write> RowWriter<'a, W> {
+    pub(crate) fn new(f: &'a mut W, header: ImageHeader) -> Result<RowWriter<'a, W>, Error> {
+        write_header(f, &header)?;
+        let packed = if header.image_type == ImageType::PBM && header.mode == Mode::BINARY {
+            vec![0; header.width.div_ceil(8) as usize]
+        } else {
+            vec![]
+        };
+        Ok(RowWriter{f, header, row: 0, packed})
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.header.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+
+    /// The number of bytes each row must have.
+    pub fn row_len(&self) -> usize {
+        loaded_row_len(&self.header)
+    }
+
+    /// Writes the next row of the image.
+    ///
+    /// In ASCII mode long rows are split over several lines so that no line is more than 70
+    /// characters. Each row starts on a new line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DimensionMismatch` if `row` is not `row_len` bytes long, `RowCount` if every row
+    /// has already been written, and `SampleOutOfRange` if a sample is greater than the max value.
+    /// Nothing is written when an error is returned, other than IO errors from the writer.
+    pub fn write_row(&mut self, row: &[u8]) -> Result<(), Error> {
+        if row.len() != self.row_len() {
+            return Result::Err(Error::DimensionMismatch{expected: self.row_len(), found: row.len()});
+        }
+        if self.row >= self.header.height {
+            return Result::Err(Error::RowCount{expected: self.header.height, found: self.row + 1});
+        }
+        // Any non-zero pbm pixel is black, so only the other formats have samples to check.
+        if self.header.image_type != ImageType::PBM {
+            check_samples(row, &self.header, &mut RasterCheck::strict())?;
+        }
+        match (self.header.image_type == ImageType::PBM, self.header.mode) {
+            (true, Mode::ASCII) => self.write_ascii(row.iter().map(|v| if *v == 0 { 0 } else { 1 }))?,
+            (true, Mode::BINARY) => {
+                pack_bits(row, &mut self.packed);
+                self.f.write_all(&self.packed)?;
+            },
+            (false, Mode::ASCII) => {
+                if self.header.depth == BitDepth::EIGHT {
+                    self.write_ascii(row.iter().map(|v| *v as u16))?;
+                } else {
+                    self.write_ascii(row.chunks(2).map(|v| ((v[0] as u16) << 8) + v[1] as u16))?;
+                }
+            },
+            (false, Mode::BINARY) => self.f.write_all(row)?,
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    /// Writes a row of samples as decimal numbers separated by spaces.
+    fn write_ascii<I: Iterator<Item = u16>>(&mut self, samples: I) -> Result<(), Error> {
+        let mut line = String::new();
+        for sample in samples {
+            let v = sample.to_string();
+            if !line.is_empty() {
+                // start a new line rather than go over the limit.
+                if line.len() + 1 + v.len() > MAX_LINE {
+                    line.push('\n');
+                    self.f.write_all(line.as_bytes())?;
+                    line.clear();
+                } else {
+                    line.push(' ');
+                }
+            }
+            line.push_str(&v);
+        }
+        line.push('\n');
+        self.f.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Finishes the image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RowCount` if fewer rows were written than the height of the image. IO errors from
+    /// flushing the writer are also returned.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.row != self.header.height {
+            return Result::Err(Error::RowCount{expected: self.header.height, found: self.row});
+        }
+        self.f.flush()?;
+        Ok(())
+    }
+}
+
+/// Saves a whole image whose rows start `stride` bytes apart in `dat`.
+///
+/// Every sample is checked before the header is written, so nothing is written for data that
+/// can't be saved. `dat` must already be checked to hold every row.
+pub(crate) fn write_image<W: Write>(f: &mut W, header: ImageHeader, dat: &[u8], stride: usize) -> Result<(), Error> {
+    let len = loaded_row_len(&header);
+    let rows = (0..header.height as usize).map(|i| &dat[i * stride..i * stride + len]);
+    if header.image_type != ImageType::PBM {
+        for row in rows.clone() {
+            check_samples(row, &header, &mut RasterCheck::strict())?;
+        }
+    }
+    let mut writer = RowWriter::new(f, header)?;
+    for row in rows {
+        writer.write_row(row)?;
+    }
+    writer.finish()
+}
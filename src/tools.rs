@@ -1,115 +1,1281 @@
-use std::io;
-use BitDepth;
-use Mode;
-
-pub struct ImageHeader {
-    pub width:u32,
-    pub height:u32,
-    pub dat_start:usize,
-    pub image_type: ImageType,
-    pub depth: BitDepth,
-    pub mode: Mode,
-}
-
-#[derive(PartialEq, Eq, Debug)]
-pub enum ImageType {
-    PBM,
-    PGM,
-    PPM,
-}
-
-/// Finds if the character is a whitespace
-///
-/// White spaces are blanks, TABs, CRs, and LFs
-fn is_whitespace(inpt: u8) -> bool {
-    inpt == 9 || inpt == 10 || inpt == 13 || inpt == 32
-}
-
-/// Finds if the character is a number.
-fn is_number(inpt: u8) -> bool {
-    inpt > 47 && inpt < 58
-}
-
-/// Reads the header from an input data stream.
-///
-/// Parses the input data to try to get the image size, file type, and bit depth. The file formats
-/// contain a magic number, height and width, optional comments, and bit depth depending on the
-/// file. These are seperated by whitespace aside from comments, which are only ended with a
-/// newline.
-pub fn get_header(dat:&Vec<u8>) ->  Result<ImageHeader, io::Error> {
-
-    // Test that the magic number is valid
-    if dat[0] != 80 ||  dat[1] < 49 || dat[1] > 55 {
-        return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Input file is not a netbpm file."));
-    }
-
-    // What file format does the magic number say we have
-    let image_type = match dat[1] {
-        49 => ImageType::PBM,
-        50 => ImageType::PGM,
-        51 => ImageType::PPM,
-        52 => ImageType::PBM,
-        53 => ImageType::PGM,
-        54 => ImageType::PPM,
-        _ => return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Input file is an unsupported netbpm type.")),
-    };
-
-    // Does the magic number say we're binary or ascii
-    let image_mode = if dat[1] > 51 && dat[1] < 55 {
-        Mode::BINARY
-    } else {
-        Mode::ASCII
-    };
-
-    // The data values we'll be storing.
-    let mut width:u32 = 0;
-    let mut height:u32 = 0;
-    let mut bit_size:u32 = 0;
-
-    // values we'll use to keep track of where in the header we are.
-    let mut data_start:usize = 0; // The index where the pixel data starts.
-    let mut header_part = -1;     // What part of the header we're reading.
-                                  //         0 = width, 1 = height, 2 = bit size
-    let mut skip = false;         // Used to skip comments.
-
-    // Read in our actual data.
-    for i in 2..dat.len() {
-        data_start += 1;
-        if skip && dat[i] == 10 { // comments end only at a LF (newline)
-            skip = false;
-        } else if !skip {
-            if dat[i] == b'#' { // start of comments
-                skip = true;
-            } else if is_number(dat[i]) { // all data we want to save should be numeric
-                match header_part {
-                    0 => width = (width * 10) + (dat[i] - 48) as u32,
-                    1 => height = (height * 10) + (dat[i] - 48) as u32,
-                    2 => bit_size = (bit_size * 10) + (dat[i] - 48) as u32,
-                    _ => return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Loader reading past end of header.")),
-                }
-            } else if is_whitespace(dat[i]) { // witespace triggers a new part of the header
-                header_part += 1;
-                // check to see if we've finished reading the header
-                if image_type == ImageType::PBM && header_part > 1 {
-                    break;
-                } else if (image_type == ImageType::PGM || image_type == ImageType::PPM) && header_part > 2 {
-                    break;
-                }
-            } else { // a non-numeric, non-whitespace character outside of a comment is an error.
-                return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Unexpected character in file header. Character: {}", dat[i])));
-            }
-        }
-    }
-
-    // Move the data start to the point after the last number read in the header. Increment by
-    // two to discard the white space following the end of the header.
-    data_start += 2;
-
-    let bits = if bit_size > 255 {
-        BitDepth::SIXTEEN
-    } else {
-        BitDepth::EIGHT
-    };
-
-    Ok(ImageHeader{width:width, height:height, dat_start:data_start, image_type:image_type, depth:bits, mode:image_mode})
-}
+use std::io;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::mem;
+use BitDepth;
+use Comment;
+use Error;
+use ImageType;
+use ImageView;
+use Limits;
+use Location;
+use Warning;
+use Mode;
+
+/// The information stored in the header of an image.
+#[derive(Clone, Debug)]
+pub struct ImageHeader {
+    pub width:u32,
+    pub height:u32,
+    /// The number of bytes from the start of the header to the start of the raster.
+    pub dat_start:usize,
+    pub image_type: ImageType,
+    pub depth: BitDepth,
+    /// The largest value a sample can have. For pbm images this is always 1.
+    pub max_val: u16,
+    /// Whether the raster is stored in ASCII or binary format. pam images are always binary.
+    pub mode: Mode,
+    /// The number of samples in each pixel.
+    pub channels: u32,
+    /// The tuple type of a pam image. Empty for the other formats.
+    pub tuple_type: String,
+    /// The comments in the header, in the order they appear.
+    pub comments: Vec<Comment>,
+}
+
+impl ImageHeader {
+    /// The number of bytes in the raster when it is stored in binary format.
+    ///
+    /// pbm rows are packed eight pixels to a byte. ASCII rasters take up more space than this as
+    /// each sample is written as a decimal number.
+    pub fn raster_len(&self) -> usize {
+        raster_len(self)
+    }
+}
+
+/// Finds if the character is a whitespace
+///
+/// White spaces are blanks, TABs, CRs, and LFs
+pub fn is_whitespace(inpt: u8) -> bool {
+    inpt == 9 || inpt == 10 || inpt == 13 || inpt == 32
+}
+
+/// Finds if the character is a number.
+pub fn is_number(inpt: u8) -> bool {
+    inpt > 47 && inpt < 58
+}
+
+/// Reads the header from an input data stream.
+///
+/// Parses the input data to try to get the image size, file type, and bit depth. The file formats
+/// contain a magic number, height and width, optional comments, and bit depth depending on the
+/// file. The values can be separated by any amount of whitespace and comments, and a comment can
+/// come straight after a value. Exactly one whitespace character follows the last value, after
+/// which the raster begins.
+pub fn get_header(dat:&[u8]) ->  Result<ImageHeader, Error> {
+
+    // Data that stops partway through the header might still be the start of a valid file.
+    if dat.is_empty() || (dat.len() < 2 && dat[0] == 80) {
+        return Result::Err(Error::Truncated);
+    }
+
+    // Test that the magic number is valid
+    if dat.len() < 2 || dat[0] != 80 ||  dat[1] < 49 || dat[1] > 55 {
+        return Result::Err(Error::BadMagicNumber{expected: None});
+    }
+
+    // What file format does the magic number say we have
+    let image_type = match dat[1] {
+        49 => ImageType::PBM,
+        50 => ImageType::PGM,
+        51 => ImageType::PPM,
+        52 => ImageType::PBM,
+        53 => ImageType::PGM,
+        54 => ImageType::PPM,
+        55 => return get_pam_header(dat),
+        _ => return Result::Err(Error::BadMagicNumber{expected: None}),
+    };
+
+    // Does the magic number say we're binary or ascii
+    let image_mode = if dat[1] > 51 && dat[1] < 55 {
+        Mode::BINARY
+    } else {
+        Mode::ASCII
+    };
+
+    let mut reader = HeaderReader{dat, pos: 2, values: 0, comments: vec![]};
+    let width = reader.number()?;
+    let height = reader.number()?;
+
+    // pbm files have no max value in the header. Their pixels are always either 0 or 1.
+    let max_val = if image_type == ImageType::PBM {
+        1
+    } else {
+        check_max_val(reader.number()?)?
+    };
+    reader.end()?;
+
+    let channels = match image_type {
+        ImageType::PPM => 3,
+        _ => 1,
+    };
+
+    Ok(ImageHeader{width, height, dat_start:reader.pos, image_type, depth:depth_for(max_val), max_val,
+                   mode:image_mode, channels, tuple_type:String::new(), comments:reader.comments})
+}
+
+/// Reads the values of a pbm, pgm, or ppm header one at a time.
+struct HeaderReader<'a> {
+    dat: &'a [u8],
+    /// The index of the next character to read.
+    pos: usize,
+    /// The number of values that have been read, not counting the magic number.
+    values: u32,
+    comments: Vec<Comment>,
+}
+
+impl<'a> HeaderReader<'a> {
+    /// Reads the next value in the header along with the whitespace and comments before it.
+    ///
+    /// The width and height must fit in a `u32`. A larger max value is returned as `u32::MAX` so
+    /// that it is rejected as an unsupported max value.
+    fn number(&mut self) -> Result<u32, Error> {
+        self.separator()?;
+        let start = self.pos;
+        let mut value:u32 = 0;
+        while let Some(&c) = self.dat.get(self.pos) {
+            if !is_number(c) {
+                if self.pos == start {
+                    return Result::Err(self.unexpected(c));
+                }
+                self.values += 1;
+                return Ok(value);
+            }
+            let digit = (c - 48) as u32;
+            value = match value.checked_mul(10).and_then(|v| v.checked_add(digit)) {
+                Some(v) => v,
+                None if self.values > 1 => u32::MAX,
+                None => {
+                    return Result::Err(Error::InvalidHeader(
+                        format!("Width or height in header is too large at {}.", location(self.dat, start))));
+                },
+            };
+            self.pos += 1;
+        }
+        Result::Err(Error::Truncated)
+    }
+
+    /// Skips the whitespace and comments before a value. There must be at least one.
+    fn separator(&mut self) -> Result<(), Error> {
+        let start = self.pos;
+        while let Some(&c) = self.dat.get(self.pos) {
+            if is_whitespace(c) {
+                self.pos += 1;
+            } else if c == b'#' {
+                self.comment()?;
+            } else if self.pos == start {
+                return Result::Err(self.unexpected(c));
+            } else {
+                return Ok(());
+            }
+        }
+        Result::Err(Error::Truncated)
+    }
+
+    /// Reads the single whitespace character, or comment, that ends the header.
+    fn end(&mut self) -> Result<(), Error> {
+        match self.dat.get(self.pos) {
+            Some(&c) if is_whitespace(c) => {
+                self.pos += 1;
+                Ok(())
+            },
+            Some(&b'#') => self.comment(),
+            Some(&c) => Result::Err(self.unexpected(c)),
+            None => Result::Err(Error::Truncated),
+        }
+    }
+
+    /// Reads a comment, which runs from a `#` to the end of the line.
+    ///
+    /// Comments end at either a LF or a CR. The character that ends the comment is part of it.
+    fn comment(&mut self) -> Result<(), Error> {
+        let start = self.pos + 1;
+        match self.dat[start..].iter().position(|&c| c == 10 || c == 13) {
+            Some(n) => {
+                // the comment comes after every value that has been finished.
+                let text = comment_text(&self.dat[start..start + n]);
+                self.comments.push(Comment{text, position: self.values});
+                self.pos = start + n + 1;
+                Ok(())
+            },
+            None => Result::Err(Error::Truncated),
+        }
+    }
+
+    /// Makes the error for a character that isn't allowed at the current position.
+    fn unexpected(&self, character: u8) -> Error {
+        Error::UnexpectedCharacter{character, location: location(self.dat, self.pos)}
+    }
+}
+
+/// Finds the line and column of a byte in a header.
+pub fn location(dat: &[u8], offset: usize) -> Location {
+    let before = &dat[..offset.min(dat.len())];
+    let line_start = before.iter().rposition(|&c| c == 10).map_or(0, |n| n + 1);
+    let line = before.iter().filter(|&&c| c == 10).count() + 1;
+    Location{offset, line, column: offset - line_start + 1}
+}
+
+/// Gets the text of a comment from the characters after the `#`.
+///
+/// A single space at the start and the line ending are not part of the text.
+fn comment_text(dat: &[u8]) -> String {
+    let mut text = String::from_utf8_lossy(dat).into_owned();
+    if text.ends_with('\r') {
+        text.pop();
+    }
+    if text.starts_with(' ') {
+        text.remove(0);
+    }
+    text
+}
+
+/// Reads the header of a pam file.
+///
+/// The pam header is different from the other formats. After the magic number it is a series of
+/// lines, each of which holds a keyword and its value. Lines starting with `#` are comments and
+/// blank lines are ignored. The header ends with a line holding only `ENDHDR`, after which the
+/// raster begins. `WIDTH`, `HEIGHT`, `DEPTH`, and `MAXVAL` must all be present. `TUPLTYPE` is
+/// optional and, if it is given more than once, the values are joined with a space.
+fn get_pam_header(dat:&[u8]) -> Result<ImageHeader, Error> {
+    let mut width:Option<u32> = None;
+    let mut height:Option<u32> = None;
+    let mut channels:Option<u32> = None;
+    let mut max_val:Option<u32> = None;
+    let mut tuple_type = String::new();
+    let mut comments:Vec<Comment> = vec![];
+    let mut fields:u32 = 0;
+
+    // The magic number must be on a line by itself.
+    if dat.len() < 3 {
+        return Result::Err(Error::Truncated);
+    }
+    if !is_whitespace(dat[2]) {
+        return Result::Err(Error::InvalidHeader("Malformed pam header.".to_string()));
+    }
+
+    let mut pos:usize = 3;
+    loop {
+        // Find the end of the current line. Running out of data before ENDHDR is an error.
+        let end = match dat[pos..].iter().position(|&c| c == 10) {
+            Some(n) => pos + n,
+            None => return Result::Err(Error::Truncated),
+        };
+        let raw = &dat[pos..end];
+        let line = String::from_utf8_lossy(raw);
+        let line_start = location(dat, pos);
+        pos = end + 1;
+
+        let line = line.trim();
+        if line.starts_with('#') {
+            let hash = raw.iter().position(|&c| c == b'#').unwrap_or(0);
+            comments.push(Comment{text: comment_text(&raw[hash + 1..]), position: fields});
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if line == "ENDHDR" {
+            break;
+        }
+        fields += 1;
+
+        let (key, val) = match line.find(|c: char| c.is_whitespace()) {
+            Some(n) => (&line[..n], line[n..].trim()),
+            None => (line, ""),
+        };
+        match key {
+            "WIDTH" => width = Some(parse_pam_value(key, val, line_start)?),
+            "HEIGHT" => height = Some(parse_pam_value(key, val, line_start)?),
+            "DEPTH" => {
+                let depth = parse_pam_value(key, val, line_start)?;
+                if depth == 0 {
+                    return Result::Err(Error::InvalidHeader(format!("Pam header at {} has a DEPTH of 0.", line_start)));
+                }
+                channels = Some(depth);
+            },
+            "MAXVAL" => max_val = Some(parse_pam_value(key, val, line_start)?),
+            "TUPLTYPE" => {
+                if !tuple_type.is_empty() {
+                    tuple_type.push(' ');
+                }
+                tuple_type.push_str(val);
+            },
+            _ => return Result::Err(Error::InvalidHeader(format!("Unexpected pam header line at {}: {}", line_start, line))),
+        }
+    }
+
+    match (width, height, channels, max_val) {
+        (Some(width), Some(height), Some(channels), Some(max_val)) => {
+            let max_val = check_max_val(max_val)?;
+            Ok(ImageHeader{width, height, dat_start:pos, image_type:ImageType::PAM, depth:depth_for(max_val),
+                           max_val, mode:Mode::BINARY, channels, tuple_type, comments})
+        },
+        _ => Result::Err(Error::InvalidHeader("Pam header is missing WIDTH, HEIGHT, DEPTH, or MAXVAL.".to_string())),
+    }
+}
+
+/// Parses the numeric value of a pam header line.
+fn parse_pam_value(key: &str, val: &str, line_start: Location) -> Result<u32, Error> {
+    match val.parse::<u32>() {
+        Ok(v) => Ok(v),
+        Err(_) => Result::Err(Error::InvalidHeader(format!("Invalid value for {} in pam header at {}: {}", key, line_start, val))),
+    }
+}
+
+/// Checks that a max value read from a header is one the formats allow.
+///
+/// The standard allows any max value greater than 0 and less than 65536.
+fn check_max_val(max_val: u32) -> Result<u16, Error> {
+    if max_val == 0 || max_val > 65535 {
+        return Result::Err(Error::UnsupportedMaxVal(max_val));
+    }
+    Ok(max_val as u16)
+}
+
+/// Finds the number of bytes used to store each sample for a given max value.
+///
+/// Samples are stored in one byte if the max value is less than 256 and two bytes otherwise.
+pub fn depth_for(max_val: u16) -> BitDepth {
+    if max_val > 255 {
+        BitDepth::SIXTEEN
+    } else {
+        BitDepth::EIGHT
+    }
+}
+
+/// Finds the max value that uses the full range of a bit depth.
+pub fn full_max_val(depth: &BitDepth) -> u16 {
+    match *depth {
+        BitDepth::EIGHT => 255,
+        BitDepth::SIXTEEN => 65535,
+    }
+}
+
+/// Rescales image data so that it uses the full range of its bit depth.
+///
+/// Each sample is scaled from the range `0..=max_val` to `0..=255` for eight-bit data or to
+/// `0..=65535` for sixteen-bit data, rounding to the nearest value. Sixteen-bit samples are
+/// stored as two bytes with the most significant byte first. Samples greater than the max value
+/// are clamped to the top of the range.
+pub fn rescale(dat: &mut [u8], depth: &BitDepth, max_val: u16) {
+    let target = full_max_val(depth) as u32;
+    let max_val = max_val as u32;
+    if max_val == target || max_val == 0 {
+        return;
+    }
+    let scale = |v: u32| -> u32 { (v.min(max_val) * target + max_val / 2) / max_val };
+    match *depth {
+        BitDepth::EIGHT => {
+            for v in dat.iter_mut() {
+                *v = scale(*v as u32) as u8;
+            }
+        },
+        BitDepth::SIXTEEN => {
+            for pair in dat.chunks_mut(2) {
+                if pair.len() == 2 {
+                    let v = scale(((pair[0] as u32) << 8) + pair[1] as u32);
+                    pair[0] = (v >> 8) as u8;
+                    pair[1] = (v & 255) as u8;
+                }
+            }
+        },
+    }
+}
+
+/// Converts samples stored as bytes into `u16`s.
+///
+/// Sixteen-bit samples are pairs of bytes with the most significant byte first. Eight-bit samples
+/// are widened without being scaled.
+pub fn samples_to_u16(dat: &[u8], depth: &BitDepth) -> Vec<u16> {
+    match *depth {
+        BitDepth::EIGHT => dat.iter().map(|&v| v as u16).collect(),
+        BitDepth::SIXTEEN => dat.chunks(2).map(|v| ((v[0] as u16) << 8) + *v.get(1).unwrap_or(&0) as u16).collect(),
+    }
+}
+
+/// Finds the number of bytes in each row of the data given to an encoder.
+///
+/// pbm data has one byte for each pixel, so it uses a max value of 1.
+pub fn encoder_row_len(width: u32, channels: u32, max_val: u16) -> Result<usize, Error> {
+    let bytes = if depth_for(max_val) == BitDepth::SIXTEEN { 2 } else { 1 };
+    match (width as usize).checked_mul(channels as usize).and_then(|n| n.checked_mul(bytes)) {
+        Some(len) => Ok(len),
+        None => Result::Err(Error::InvalidParameter("The image is too large to save.")),
+    }
+}
+
+/// Checks that the data given to an encoder holds exactly `height` rows of `row_len` bytes.
+pub fn check_len(dat: &[u8], row_len: usize, height: u32) -> Result<(), Error> {
+    let expected = row_len.saturating_mul(height as usize);
+    if dat.len() != expected {
+        return Result::Err(Error::DimensionMismatch{expected, found: dat.len()});
+    }
+    Ok(())
+}
+
+/// Checks that the data given to an encoder holds `height` rows of `row_len` bytes, with each row
+/// starting `stride` bytes after the one before it.
+///
+/// Any bytes between the rows and after the last row are ignored.
+pub fn check_stride(dat: &[u8], row_len: usize, height: u32, stride: usize) -> Result<(), Error> {
+    if stride < row_len {
+        return Result::Err(Error::InvalidParameter("The stride can not be less than the length of a row."));
+    }
+    let expected = match height {
+        0 => 0,
+        _ => stride.saturating_mul(height as usize - 1).saturating_add(row_len),
+    };
+    if dat.len() < expected {
+        return Result::Err(Error::DimensionMismatch{expected, found: dat.len()});
+    }
+    Ok(())
+}
+
+/// Converts `u16` samples into the bytes saved for an image with the given max value.
+///
+/// Samples use one byte if the max value is less than 256 and two bytes, most significant byte
+/// first, otherwise.
+pub fn samples_from_u16(dat: &[u16], max_val: u16) -> Result<Vec<u8>, Error> {
+    if dat.iter().any(|&v| v > max_val) {
+        return Result::Err(Error::InvalidParameter("Samples can not be greater than the max value."));
+    }
+    let mut out:Vec<u8> = Vec::with_capacity(dat.len() * 2);
+    for &v in dat {
+        push_sample(&mut out, &depth_for(max_val), v);
+    }
+    Ok(out)
+}
+
+/// Finds the number of bytes in a binary raster described by a header.
+///
+/// pbm rows are packed eight pixels to a byte with each row padded to a whole byte. The other
+/// formats use one or two bytes per sample depending on the bit depth.
+fn raster_len(header: &ImageHeader) -> usize {
+    let width = header.width as usize;
+    let height = header.height as usize;
+    // Headers from `probe` haven't been checked against any limits, so the size saturates
+    // rather than overflowing.
+    if header.image_type == ImageType::PBM {
+        return width.div_ceil(8).saturating_mul(height);
+    }
+    let bytes = match header.depth {
+        BitDepth::EIGHT => 1,
+        BitDepth::SIXTEEN => 2,
+    };
+    width.saturating_mul(height).saturating_mul(header.channels as usize).saturating_mul(bytes)
+}
+
+/// Checks the size of an image against the limits set on a decoder.
+///
+/// `sample_bytes` is the number of bytes each sample takes up once loaded. The size of a whole
+/// image and of a single row are both checked against the allocation limit, and neither can be
+/// larger than the most that can ever be allocated, so the sizes used by the decoders can't
+/// overflow.
+pub fn check_limits(limits: &Limits, width: u32, height: u32, channels: u32, sample_bytes: u64) -> Result<(), Error> {
+    if width > limits.max_width {
+        return Result::Err(Error::LimitExceeded{limit: "width", value: width as u64, max: limits.max_width as u64});
+    }
+    if height > limits.max_height {
+        return Result::Err(Error::LimitExceeded{limit: "height", value: height as u64, max: limits.max_height as u64});
+    }
+    let pixels = width as u64 * height as u64;
+    if pixels > limits.max_pixels {
+        return Result::Err(Error::LimitExceeded{limit: "pixel count", value: pixels, max: limits.max_pixels});
+    }
+    let max_alloc = limits.max_alloc.min(isize::MAX as u64);
+    let row = (width as u64).saturating_mul(channels as u64).saturating_mul(sample_bytes);
+    let total = row.saturating_mul(height as u64).max(row);
+    if total > max_alloc {
+        return Result::Err(Error::LimitExceeded{limit: "allocation", value: total, max: max_alloc});
+    }
+    Ok(())
+}
+
+/// Finds the number of bytes in each row of a loaded image.
+///
+/// Loaded pbm images use one byte for each pixel, so unlike `raster_len` their rows aren't packed.
+/// This is also the length of the rows read and written by `Rows` and `RowWriter`.
+pub(crate) fn loaded_row_len(header: &ImageHeader) -> usize {
+    let bytes = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    header.width as usize * header.channels as usize * bytes
+}
+
+/// Loads the raster of an image.
+///
+/// The stream must be at the start of the raster and is left at the end of it. Pbm images are
+/// unpacked to one byte per pixel.
+///
+/// # Errors
+///
+/// Returns `Truncated` if the data ends before the end of the raster, `UnexpectedRasterCharacter`
+/// if an ASCII raster holds something other than samples, and `SampleOutOfRange` if a sample is
+/// greater than the max value.
+pub fn load_raster<R: Read>(stream: &mut Stream<R>, header: &ImageHeader) -> Result<Vec<u8>, Error> {
+    let dat = read_raster(stream, header, &mut RasterCheck::strict())?;
+    if dat.len() < loaded_row_len(header) * header.height as usize {
+        return Result::Err(Error::Truncated);
+    }
+    Ok(dat)
+}
+
+/// Checks that a buffer holds exactly the samples of an image.
+///
+/// Buffers of bytes use the layout `load_raster` returns and buffers of `u16` have one sample in
+/// each place. The sizes in a `DimensionMismatch` error are in bytes, as if each `u16` were stored
+/// in two bytes.
+pub fn check_buffer<T>(header: &ImageHeader, buf: &[T]) -> Result<(), Error> {
+    let size = mem::size_of::<T>();
+    let expected = if size == 1 {
+        loaded_row_len(header) * header.height as usize
+    } else {
+        header.width as usize * header.height as usize * header.channels as usize * size
+    };
+    let found = mem::size_of_val(buf);
+    if found != expected {
+        return Result::Err(Error::DimensionMismatch{expected, found});
+    }
+    Ok(())
+}
+
+/// Loads the raster of an image into a buffer instead of allocating one.
+///
+/// `out` must be exactly the size of the loaded raster, in the same layout as `load_raster`
+/// returns. The stream must be at the start of the raster and is left at the end of it.
+///
+/// # Errors
+///
+/// Returns `DimensionMismatch` if `out` is the wrong size, before any of the raster is read.
+/// Otherwise this returns the same errors as `load_raster`.
+pub fn load_raster_into<R: Read>(stream: &mut Stream<R>, header: &ImageHeader, out: &mut [u8]) -> Result<(), Error> {
+    check_buffer(header, out)?;
+    if fill_raster(stream, header, out, &mut RasterCheck::strict())? < out.len() {
+        return Result::Err(Error::Truncated);
+    }
+    Ok(())
+}
+
+/// Loads the samples of a pgm or ppm image into a buffer of `u16`s.
+///
+/// This works the same as `load_raster_into` except that each sample takes up one place in `out`.
+/// Eight-bit samples are widened without being scaled. The size in a `DimensionMismatch` error is
+/// given in bytes, as if the samples were stored in two bytes each.
+pub fn load_raster_into_u16<R: Read>(stream: &mut Stream<R>, header: &ImageHeader, out: &mut [u16]) -> Result<(), Error> {
+    check_buffer(header, out)?;
+    let mut check = RasterCheck::strict();
+    // Samples are read a block at a time so no buffer needs to be allocated.
+    let step = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    let mut block = [0u8; 8192];
+    for samples in out.chunks_mut(block.len() / step) {
+        let bytes = &mut block[..samples.len() * step];
+        if fill_raster(stream, header, bytes, &mut check)? < bytes.len() {
+            return Result::Err(Error::Truncated);
+        }
+        for (sample, b) in samples.iter_mut().zip(bytes.chunks(step)) {
+            *sample = b.iter().fold(0, |v, &b| (v << 8) | b as u16);
+        }
+    }
+    Ok(())
+}
+
+/// Loads as much of the raster of an image as there is.
+///
+/// This works the same as `load_raster` except that if the data ends early the rest of the raster
+/// is filled with zeros instead of returning an error. The number of rows that were read in full
+/// is returned along with the data.
+pub fn load_raster_partial<R: Read>(stream: &mut Stream<R>, header: &ImageHeader) -> Result<(Vec<u8>, u32), Error> {
+    let mut dat = read_raster(stream, header, &mut RasterCheck::strict())?;
+    let rows = pad_raster(&mut dat, header);
+    Ok((dat, rows))
+}
+
+/// Loads the raster of an image, working around any problems in it.
+///
+/// Characters that don't belong in an ASCII raster are skipped, samples greater than the max value
+/// are lowered to it, and a raster that ends early is filled with zeros. Anything after the raster
+/// that isn't the start of another image is skipped. A warning is returned for each kind of
+/// problem found.
+pub fn load_raster_lenient<R: Read>(stream: &mut Stream<R>, header: &ImageHeader) -> Result<(Vec<u8>, Vec<Warning>), Error> {
+    let mut check = RasterCheck::lenient();
+    let mut dat = read_raster(stream, header, &mut check)?;
+    let mut warnings = check.warnings();
+    if dat.len() < loaded_row_len(header) * header.height as usize {
+        let rows = pad_raster(&mut dat, header);
+        warnings.push(Warning::Truncated{rows});
+    }
+    let bytes = stream.skip_trailing()?;
+    if bytes > 0 {
+        warnings.push(Warning::TrailingData{bytes});
+    }
+    Ok((dat, warnings))
+}
+
+/// Checks that a rectangle can be loaded from an image with `load_region`.
+///
+/// This only looks at the header, so it can be called before the stream is moved past it.
+///
+/// # Errors
+///
+/// Returns `InvalidParameter` if the image is stored in ASCII or the rectangle isn't inside the
+/// image. The limits are checked against the size of the rectangle rather than the whole image.
+pub fn check_region(header: &ImageHeader, limits: &Limits, x: u32, y: u32, w: u32, h: u32) -> Result<(), Error> {
+    if header.mode != Mode::BINARY {
+        return Result::Err(Error::InvalidParameter("Regions can only be loaded from binary images."));
+    }
+    let inside = |start: u32, len: u32, size: u32| start.checked_add(len).is_some_and(|end| end <= size);
+    if !inside(x, w, header.width) || !inside(y, h, header.height) {
+        return Result::Err(Error::InvalidParameter("The region must be inside the image."));
+    }
+    let sample_bytes = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    check_limits(limits, w, h, header.channels, sample_bytes)
+}
+
+/// Loads a rectangle from the raster of a binary image.
+///
+/// Rows of a binary raster all take up the same number of bytes, so the parts of the raster
+/// outside the rectangle are skipped by seeking instead of being read. Pbm rows are packed eight
+/// pixels to a byte, so a rectangle that doesn't start on a byte boundary is shifted as it is
+/// unpacked. The stream must be at the start of the raster and is left at the end of it. The
+/// rectangle must already have been checked with `check_region`.
+///
+/// # Errors
+///
+/// Returns `Truncated` if the data ends before the end of the rectangle and `SampleOutOfRange` if
+/// a sample is greater than the max value.
+pub fn load_region<R: Read + Seek>(stream: &mut Stream<R>, header: &ImageHeader, x: u32, y: u32, w: u32, h: u32) -> Result<Vec<u8>, Error> {
+    let sample_bytes = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    let pbm = header.image_type == ImageType::PBM;
+    let pixel_len = (header.channels * sample_bytes as u32) as u64;
+    let row_len = if pbm { header.width.div_ceil(8) as u64 } else { header.width as u64 * pixel_len };
+    // The bytes of each row that hold the region.
+    let (start, len) = if !pbm {
+        (x as u64 * pixel_len, w as u64 * pixel_len)
+    } else if w > 0 {
+        (x as u64 / 8, (x as u64 + w as u64 - 1) / 8 - x as u64 / 8 + 1)
+    } else {
+        (0, 0)
+    };
+
+    let mut dat:Vec<u8> = Vec::with_capacity(if pbm { w as usize } else { len as usize } * h as usize);
+    let mut row = vec![0; len as usize];
+    stream.skip(y as u64 * row_len)?;
+    for _ in 0..h {
+        stream.skip(start)?;
+        if stream.read_into(&mut row)? < row.len() {
+            return Result::Err(Error::Truncated);
+        }
+        if pbm {
+            let shift = (x % 8) as usize;
+            dat.extend((shift..shift + w as usize).map(|bit| (row[bit / 8] >> (7 - bit % 8)) & 1));
+        } else {
+            dat.extend_from_slice(&row);
+        }
+        stream.skip(row_len - start - len)?;
+    }
+    stream.skip((header.height - y - h) as u64 * row_len)?;
+    if !pbm {
+        check_samples(&dat, header, &mut RasterCheck::strict())?;
+    }
+    Ok(dat)
+}
+
+/// Makes a view of a binary image stored in `dat` without copying the raster.
+///
+/// `format` is the name used in the error if the image isn't of type `image_type`. Any data after
+/// the raster is ignored.
+///
+/// # Errors
+///
+/// Returns `InvalidParameter` if the image is stored in ASCII, `Truncated` if `dat` ends before
+/// the end of the raster, and `SampleOutOfRange` if a sample is greater than the max value, as
+/// well as all header parsing errors.
+pub fn view_raster<'a>(dat: &'a [u8], image_type: ImageType, format: &'static str) -> Result<ImageView<'a>, Error> {
+    let header = get_header(dat)?;
+    if header.image_type != image_type {
+        return Result::Err(Error::BadMagicNumber{expected: Some(format)});
+    }
+    if header.mode != Mode::BINARY {
+        return Result::Err(Error::InvalidParameter("Only binary images can be viewed without copying."));
+    }
+    let len = raster_len(&header);
+    if dat.len() - header.dat_start < len {
+        return Result::Err(Error::Truncated);
+    }
+    let raster = &dat[header.dat_start..header.dat_start + len];
+
+    check_samples(raster, &header, &mut RasterCheck::strict())?;
+    Ok(ImageView{width: header.width, height: header.height, dat: raster, depth: header.depth,
+                 max_val: header.max_val, comments: header.comments})
+}
+
+/// Fills the rest of a raster that ended early with zeros.
+///
+/// Returns the number of rows that were read in full.
+fn pad_raster(dat: &mut Vec<u8>, header: &ImageHeader) -> u32 {
+    let row_len = loaded_row_len(header);
+    // Images with empty rows have nothing to be missing.
+    let rows = match dat.len().checked_div(row_len) {
+        Some(rows) => rows.min(header.height as usize) as u32,
+        None => header.height,
+    };
+    dat.resize(row_len * header.height as usize, 0);
+    rows
+}
+
+/// Reads the raster of an image, stopping early if the data runs out.
+///
+/// The raster is read a row at a time so no more is allocated than the data holds, however large
+/// the header says the image is.
+fn read_raster<R: Read>(stream: &mut Stream<R>, header: &ImageHeader, check: &mut RasterCheck) -> Result<Vec<u8>, Error> {
+    let row_len = loaded_row_len(header);
+    let mut dat:Vec<u8> = vec![];
+    if row_len == 0 {
+        return Ok(dat);
+    }
+    for _ in 0..header.height {
+        let start = dat.len();
+        dat.resize(start + row_len, 0);
+        let filled = fill_raster(stream, header, &mut dat[start..], check)?;
+        if filled < row_len {
+            dat.truncate(start + filled);
+            break;
+        }
+    }
+    Ok(dat)
+}
+
+/// Reads samples from the raster of an image into `out`.
+///
+/// Pbm images are unpacked to one byte per pixel, and `out` must hold a whole number of their
+/// rows. Returns the number of bytes filled, which is less than the length of `out` only if the
+/// data runs out. ASCII rasters are read up to the character after the last sample so no part of
+/// the next image is used.
+pub fn fill_raster<R: Read>(stream: &mut Stream<R>, header: &ImageHeader, out: &mut [u8],
+                            check: &mut RasterCheck) -> Result<usize, Error> {
+    match (header.image_type, header.mode) {
+        (ImageType::PBM, Mode::ASCII) => {
+            for (i, pixel) in out.iter_mut().enumerate() {
+                match next_ascii_bit(stream, check)? {
+                    Some(bit) => *pixel = bit,
+                    None => return Ok(i),
+                }
+            }
+        },
+        (ImageType::PBM, Mode::BINARY) => {
+            // Each row starts on a new byte.
+            let mut filled = 0;
+            for row in out.chunks_mut(header.width.max(1) as usize) {
+                for pixels in row.chunks_mut(8) {
+                    match stream.next_byte()? {
+                        Some(packed) => unpack_bits(&[packed], pixels),
+                        None => return Ok(filled),
+                    }
+                    filled += pixels.len();
+                }
+            }
+        },
+        (_, Mode::ASCII) => {
+            let step = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+            for (i, sample) in out.chunks_mut(step).enumerate() {
+                let num = match next_ascii_sample(stream, check)? {
+                    Some(num) => check.sample(num, header.max_val)?,
+                    None => return Ok(i * step),
+                };
+                sample.copy_from_slice(&num.to_be_bytes()[2 - step..]);
+            }
+        },
+        // unlike with the pbm file format we can take raw binary data with no processing.
+        (_, Mode::BINARY) => {
+            let filled = stream.read_into(out)?;
+            if check_samples(&out[..filled], header, check)? {
+                lower_samples(&mut out[..filled], header);
+            }
+            return Ok(filled);
+        },
+    }
+    Ok(out.len())
+}
+
+/// Checks that every sample of a binary raster is no greater than the max value.
+///
+/// A strict check returns an error for the first sample that is too large. A lenient check counts
+/// them instead and returns `true` if any need to be lowered with `lower_samples`.
+pub fn check_samples(dat: &[u8], header: &ImageHeader, check: &mut RasterCheck) -> Result<bool, Error> {
+    // Every sample is in range when the max value is the largest the bit depth can hold.
+    if header.max_val == full_max_val(&header.depth) {
+        return Ok(false);
+    }
+    let mut lower = false;
+    for v in binary_samples(dat, header.depth) {
+        lower |= check.sample(v, header.max_val)? as u32 != v;
+    }
+    Ok(lower)
+}
+
+/// Lowers every sample of a binary raster that is greater than the max value to the max value.
+fn lower_samples(dat: &mut [u8], header: &ImageHeader) {
+    let step = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    for sample in dat.chunks_exact_mut(step) {
+        if binary_samples(sample, header.depth).any(|v| v > header.max_val as u32) {
+            let max_val = header.max_val.to_be_bytes();
+            sample.copy_from_slice(&max_val[2 - step..]);
+        }
+    }
+}
+
+/// Reads the samples of a binary raster, each one or two bytes depending on the bit depth.
+fn binary_samples(dat: &[u8], depth: BitDepth) -> impl Iterator<Item = u32> + '_ {
+    let step = if depth == BitDepth::SIXTEEN { 2 } else { 1 };
+    dat.chunks_exact(step).map(|s| s.iter().fold(0, |v, &b| (v << 8) | b as u32))
+}
+
+/// How problems in a raster are handled while it is loaded.
+///
+/// A strict check returns the first problem as an error. A lenient check skips characters that
+/// don't belong in an ASCII raster and lowers samples greater than the max value, counting each so
+/// they can be reported as warnings.
+pub struct RasterCheck {
+    lenient: bool,
+    /// The number of bytes of an ASCII raster that have been read.
+    offset: usize,
+    /// The number of characters skipped and the offset of the first one.
+    skipped: usize,
+    first_skipped: usize,
+    /// The number of samples lowered to the max value.
+    lowered: usize,
+}
+
+impl RasterCheck {
+    pub fn strict() -> RasterCheck {
+        RasterCheck{lenient: false, offset: 0, skipped: 0, first_skipped: 0, lowered: 0}
+    }
+
+    pub fn lenient() -> RasterCheck {
+        RasterCheck{lenient: true, ..RasterCheck::strict()}
+    }
+
+    /// Checks a sample against the max value, returning the value to store.
+    ///
+    /// The sample is taken as a `u32` so plain samples too large for a `u16` are still reported.
+    pub fn sample(&mut self, value: u32, max_val: u16) -> Result<u16, Error> {
+        if value <= max_val as u32 {
+            return Ok(value as u16);
+        }
+        if !self.lenient {
+            return Result::Err(Error::SampleOutOfRange{value, max_val});
+        }
+        self.lowered += 1;
+        Ok(max_val)
+    }
+
+    /// The warnings for the problems that were worked around.
+    fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = vec![];
+        if self.skipped > 0 {
+            warnings.push(Warning::UnexpectedCharacters{count: self.skipped, offset: self.first_skipped});
+        }
+        if self.lowered > 0 {
+            warnings.push(Warning::SampleAboveMaxVal{count: self.lowered});
+        }
+        warnings
+    }
+
+    /// Gets the next byte of an ASCII raster.
+    fn next_byte<R: Read>(&mut self, stream: &mut Stream<R>) -> Result<Option<u8>, Error> {
+        let c = stream.next_byte()?;
+        if c.is_some() {
+            self.offset += 1;
+        }
+        Ok(c)
+    }
+
+    /// Handles the character just read if it isn't a digit.
+    ///
+    /// Whitespace is allowed and a comment is skipped to the end of its line. Anything else is an
+    /// error unless the check is lenient.
+    fn separator<R: Read>(&mut self, stream: &mut Stream<R>, c: u8) -> Result<(), Error> {
+        if is_whitespace(c) {
+            return Ok(());
+        }
+        if c == b'#' {
+            while let Some(c) = self.next_byte(stream)? {
+                if c == 10 || c == 13 {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+        let offset = self.offset - 1;
+        if !self.lenient {
+            return Result::Err(Error::UnexpectedRasterCharacter{character: c, offset});
+        }
+        if self.skipped == 0 {
+            self.first_skipped = offset;
+        }
+        self.skipped += 1;
+        Ok(())
+    }
+}
+
+/// Reads the next pixel from an ASCII pbm raster.
+///
+/// Pixels are the characters '0' and '1', which don't need whitespace between them. Returns `None`
+/// if the data ends before another pixel.
+pub fn next_ascii_bit<R: Read>(stream: &mut Stream<R>, check: &mut RasterCheck) -> Result<Option<u8>, Error> {
+    loop {
+        match check.next_byte(stream)? {
+            Some(48) => return Ok(Some(0)),
+            Some(49) => return Ok(Some(1)),
+            Some(c) => check.separator(stream, c)?,
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Reads the next sample from an ASCII raster.
+///
+/// Samples are separated by whitespace and comments. The character that ends the sample is used
+/// up. Returns `None` if the data ends before another sample starts. The value isn't limited to
+/// 65535 so samples too large for any max value can be reported; it saturates at `u32::MAX`.
+pub fn next_ascii_sample<R: Read>(stream: &mut Stream<R>, check: &mut RasterCheck) -> Result<Option<u32>, Error> {
+    let mut num:u32 = 0;
+    let mut in_num = false;
+    loop {
+        match check.next_byte(stream)? {
+            Some(x) if is_number(x) => {
+                num = num.saturating_mul(10).saturating_add((x - 48) as u32);
+                in_num = true;
+            },
+            Some(c) => {
+                check.separator(stream, c)?;
+                if in_num {
+                    break;
+                }
+            },
+            None if in_num => break,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(num))
+}
+
+/// Adds a sample read from an ASCII raster to the image data.
+fn push_sample(vals: &mut Vec<u8>, depth: &BitDepth, num: u16) {
+    if *depth == BitDepth::EIGHT {
+        vals.push(num as u8);
+    } else {
+        vals.push((num >> 8) as u8);  // most significant byte first.
+        vals.push((num & 255) as u8); // least significant byte second.
+    }
+}
+
+/// Writes the header for an image.
+///
+/// The header ends with a single newline so the raster can be written straight after it. Each
+/// comment is written on its own line after the number of values given by its position. Comments
+/// can't come after the last value, so any with a larger position are written just before it.
+pub fn write_header<W: Write>(f: &mut W, header: &ImageHeader) -> Result<(), Error> {
+    if header.comments.iter().any(|c| c.text.contains('\n') || c.text.contains('\r')) {
+        return Result::Err(Error::InvalidParameter("Comments can not contain a line break."));
+    }
+    let magic = match (header.image_type, header.mode) {
+        (ImageType::PBM, Mode::ASCII) => "P1",
+        (ImageType::PGM, Mode::ASCII) => "P2",
+        (ImageType::PPM, Mode::ASCII) => "P3",
+        (ImageType::PBM, Mode::BINARY) => "P4",
+        (ImageType::PGM, Mode::BINARY) => "P5",
+        (ImageType::PPM, Mode::BINARY) => "P6",
+        (ImageType::PAM, _) => "P7",
+    };
+    let mut out = magic.to_string();
+    if header.image_type == ImageType::PAM {
+        let mut lines = vec![format!("WIDTH {}", header.width), format!("HEIGHT {}", header.height),
+                             format!("DEPTH {}", header.channels), format!("MAXVAL {}", header.max_val)];
+        if !header.tuple_type.is_empty() {
+            lines.push(format!("TUPLTYPE {}", header.tuple_type));
+        }
+        out.push('\n');
+        for i in 0..lines.len() + 1 {
+            for comment in header.comments.iter().filter(|c| (c.position as usize).min(lines.len()) == i) {
+                push_comment(&mut out, comment);
+            }
+            if i < lines.len() {
+                out.push_str(&lines[i]);
+                out.push('\n');
+            }
+        }
+        out.push_str("ENDHDR\n");
+    } else {
+        let mut values = vec![header.width, header.height];
+        if header.image_type != ImageType::PBM {
+            values.push(header.max_val as u32);
+        }
+        for (i, v) in values.iter().enumerate() {
+            let mut comments = header.comments.iter().filter(|c| (c.position as usize).min(values.len() - 1) == i).peekable();
+            if comments.peek().is_some() {
+                out.push('\n');
+                for comment in comments {
+                    push_comment(&mut out, comment);
+                }
+            } else if i == 1 {
+                out.push(' ');
+            } else {
+                out.push('\n');
+            }
+            out.push_str(&v.to_string());
+        }
+        out.push('\n');
+    }
+    f.write_all(out.as_bytes())?;
+    Ok(())
+}
+
+/// Adds a comment line to a header.
+fn push_comment(out: &mut String, comment: &Comment) {
+    if comment.text.is_empty() {
+        out.push_str("#\n");
+    } else {
+        out.push_str("# ");
+        out.push_str(&comment.text);
+        out.push('\n');
+    }
+}
+
+/// Packs one row of a pbm image into the binary format.
+///
+/// Each byte of `packed` holds eight pixels with the first pixel in the most significant bit. Any
+/// non-zero pixel is stored as 1 and the padding bits at the end of the row are set to 0.
+pub fn pack_bits(row: &[u8], packed: &mut [u8]) {
+    for byte in packed.iter_mut() {
+        *byte = 0;
+    }
+    for (i, pixel) in row.iter().enumerate() {
+        if *pixel != 0 {
+            packed[i / 8] |= 128 >> (i % 8);
+        }
+    }
+}
+
+/// Unpacks one row of a binary pbm image into one byte per pixel.
+///
+/// Each byte of `packed` holds eight pixels with the first pixel in the most significant bit. The
+/// padding bits at the end of the row are ignored.
+pub fn unpack_bits(packed: &[u8], row: &mut [u8]) {
+    for (i, pixel) in row.iter_mut().enumerate().take(packed.len() * 8) {
+        *pixel = (packed[i / 8] >> (7 - i % 8)) & 1;
+    }
+}
+
+/// A source of data that may hold several images.
+///
+/// The standard allows images to be stored one after another in the same file. This reads from
+/// the source only as much as it needs to load each image, so it can be used on pipes and
+/// sockets where more images may arrive later. Data that has been read but not yet used is kept
+/// for the next image.
+pub struct Stream<R: Read> {
+    r: R,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    limits: Limits,
+}
+
+impl<R: Read> Stream<R> {
+    pub fn new(r: R) -> Stream<R> {
+        Stream{r, buf: vec![], pos: 0, eof: false, limits: Limits::default()}
+    }
+
+    /// The limits checked against each header.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Sets the limits checked against each header read after this.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Reads more data from the source into the buffer.
+    ///
+    /// Returns false if the source has no more data.
+    fn fill(&mut self) -> Result<bool, io::Error> {
+        if self.eof {
+            return Ok(false);
+        }
+        // Throw away data that has already been used before reading more.
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        let mut chunk = [0u8; 8192];
+        loop {
+            match self.r.read(&mut chunk) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Ok(false);
+                },
+                Ok(n) => {
+                    self.buf.extend_from_slice(&chunk[..n]);
+                    return Ok(true);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Result::Err(e),
+            }
+        }
+    }
+
+    /// Gets the next byte from the source.
+    pub fn next_byte(&mut self) -> Result<Option<u8>, io::Error> {
+        if self.pos >= self.buf.len() && !self.fill()? {
+            return Ok(None);
+        }
+        self.pos += 1;
+        Ok(Some(self.buf[self.pos - 1]))
+    }
+
+    /// Gets up to `len` bytes from the source.
+    ///
+    /// Fewer bytes are returned only if the source runs out of data.
+    pub fn take(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
+        let buffered = (self.buf.len() - self.pos).min(len);
+        let mut out = self.buf[self.pos..self.pos + buffered].to_vec();
+        self.pos += buffered;
+        if out.len() < len && !self.eof {
+            let needed = (len - out.len()) as u64;
+            (&mut self.r).take(needed).read_to_end(&mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Fills `out` with bytes from the source.
+    ///
+    /// Returns the number of bytes read, which is less than the length of `out` only if the source
+    /// runs out of data.
+    pub fn read_into(&mut self, out: &mut [u8]) -> Result<usize, io::Error> {
+        let buffered = (self.buf.len() - self.pos).min(out.len());
+        out[..buffered].copy_from_slice(&self.buf[self.pos..self.pos + buffered]);
+        self.pos += buffered;
+        let mut read = buffered;
+        while read < out.len() && !self.eof {
+            match self.r.read(&mut out[read..]) {
+                Ok(0) => self.eof = true,
+                Ok(n) => read += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Result::Err(e),
+            }
+        }
+        Ok(read)
+    }
+
+    /// Finds if there are any images left in the source.
+    ///
+    /// Any whitespace between the end of one image and the start of the next is skipped.
+    pub fn at_end(&mut self) -> Result<bool, io::Error> {
+        loop {
+            while self.pos < self.buf.len() && is_whitespace(self.buf[self.pos]) {
+                self.pos += 1;
+            }
+            if self.pos < self.buf.len() {
+                return Ok(false);
+            }
+            if !self.fill()? {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Reads the header of the next image.
+    ///
+    /// More data is read from the source until the whole header is available, after which the
+    /// size of the image is checked against the limits and the stream is left at the start of
+    /// the raster.
+    pub fn header(&mut self) -> Result<ImageHeader, Error> {
+        let header = self.peek_header()?;
+        self.check_size(&header)?;
+        self.skip_header(&header);
+        Ok(header)
+    }
+
+    /// Reads the header of the next image without moving past it.
+    ///
+    /// The stream is left at the start of the header until `skip_header` is called, so an image
+    /// that can't be loaded doesn't stop the images after it from being read. The size of the
+    /// image isn't checked against the limits, which is left to `check_size`.
+    pub fn peek_header(&mut self) -> Result<ImageHeader, Error> {
+        loop {
+            match get_header(&self.buf[self.pos..]) {
+                Ok(header) => return Ok(header),
+                // The header might be complete once more data has arrived, as long as it isn't
+                // already larger than the allocation limit.
+                Err(Error::Truncated) if !self.eof => {
+                    let len = (self.buf.len() - self.pos) as u64;
+                    if len > self.limits.max_alloc {
+                        return Result::Err(Error::LimitExceeded{limit: "header size", value: len, max: self.limits.max_alloc});
+                    }
+                    self.fill()?;
+                },
+                Err(e) => return Result::Err(e),
+            }
+        }
+    }
+
+    /// Checks the size of the image described by a header against the limits.
+    pub fn check_size(&self, header: &ImageHeader) -> Result<(), Error> {
+        let sample_bytes = if header.depth == BitDepth::SIXTEEN { 2 } else { 1 };
+        check_limits(&self.limits, header.width, header.height, header.channels, sample_bytes)
+    }
+
+    /// Moves past a header returned by `peek_header` to the start of the raster.
+    pub fn skip_header(&mut self, header: &ImageHeader) {
+        self.pos += header.dat_start;
+    }
+
+    /// Skips the rest of the source if it doesn't hold another image.
+    ///
+    /// Returns the number of bytes skipped, not counting whitespace before them. Data starting
+    /// with a magic number is left to be read as the next image.
+    pub fn skip_trailing(&mut self) -> Result<usize, io::Error> {
+        if self.at_end()? {
+            return Ok(0);
+        }
+        // `at_end` leaves the stream at the first character after the whitespace.
+        while self.buf.len() - self.pos < 2 && self.fill()? {}
+        let rest = &self.buf[self.pos..];
+        if rest.len() >= 2 && rest[0] == b'P' && (b"1234567Ff").contains(&rest[1]) {
+            return Ok(0);
+        }
+        let mut skipped = rest.len();
+        self.pos = self.buf.len();
+        if !self.eof {
+            skipped += io::copy(&mut self.r, &mut io::sink())? as usize;
+            self.eof = true;
+        }
+        Ok(skipped)
+    }
+
+    /// Skips the rest of the source so no more images will be read.
+    pub fn finish(&mut self) {
+        self.pos = self.buf.len();
+        self.eof = true;
+    }
+}
+
+impl<R: Read + Seek> Stream<R> {
+    /// Moves forward `len` bytes, seeking past any that haven't been read from the source yet.
+    pub fn skip(&mut self, len: u64) -> Result<(), io::Error> {
+        let buffered = (self.buf.len() - self.pos) as u64;
+        if len <= buffered {
+            self.pos += len as usize;
+            return Ok(());
+        }
+        let ahead = len - buffered;
+        if ahead > i64::MAX as u64 {
+            return Result::Err(io::Error::new(io::ErrorKind::InvalidInput, "Can not seek that far."));
+        }
+        self.r.seek(SeekFrom::Current(ahead as i64))?;
+        self.buf.clear();
+        self.pos = 0;
+        self.eof = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pam::PAMEncoder;
+use netbpm::pbm::PBMEncoder;
+use netbpm::pgm::PGMEncoder;
+use netbpm::ppm::PPMEncoder;
+use netbpm::{BitDepth,Error,ImageType,Mode};
+use std::fs;
+
+fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
+    let mut ret = true;
+    for i in 0..size {
+        ret = ret && (a[i as usize] == b[i as usize]);
+    }
+    ret
+}
+
+#[test]
+fn any_pbm() {
+    let mut encoder = PBMEncoder::new("test_0.any").unwrap();
+    let _ = encoder.save(&[1,0,0,1], 2, 2, Mode::BINARY);
+    let image = netbpm::open("test_0.any").unwrap();
+    assert_eq!(ImageType::PBM, image.image_type);
+    assert_eq!(Mode::BINARY, image.mode);
+    assert_eq!(1, image.channels);
+    assert_eq!(1, image.max_val);
+    assert!(test_arrs(4, &image.dat, &[1,0,0,1]));
+    let _ = fs::remove_file("test_0.any");
+}
+
+#[test]
+fn any_pgm() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save_max_val(&[0,0, 15,255], 2, 1, Mode::ASCII, 4095);
+    }
+    let image = netbpm::decode(&buffer[..]).unwrap();
+    assert_eq!(ImageType::PGM, image.image_type);
+    assert_eq!(Mode::ASCII, image.mode);
+    assert_eq!(1, image.channels);
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert_eq!(4095, image.max_val);
+    assert!(test_arrs(4, &image.dat, &[0,0, 15,255]));
+}
+
+#[test]
+fn any_ppm() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[255,0,0, 0,0,255], 2, 1, Mode::BINARY, BitDepth::EIGHT);
+    }
+    let image = netbpm::decode(&buffer[..]).unwrap();
+    assert_eq!(ImageType::PPM, image.image_type);
+    assert_eq!(3, image.channels);
+    assert_eq!(255, image.max_val);
+    assert!(test_arrs(6, &image.dat, &[255,0,0, 0,0,255]));
+}
+
+#[test]
+fn any_pam() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[0,255, 255,0], 2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA");
+    }
+    let image = netbpm::decode(&buffer[..]).unwrap();
+    assert_eq!(ImageType::PAM, image.image_type);
+    assert_eq!(2, image.channels);
+    assert_eq!("GRAYSCALE_ALPHA", image.tuple_type);
+    assert!(test_arrs(4, &image.dat, &[0,255, 255,0]));
+}
+
+#[test]
+fn any_not_netbpm_error() {
+    match netbpm::decode(&b"GIF89a"[..]) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::BadMagicNumber{expected} => assert_eq!(expected, None),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn any_empty_error() {
+    match netbpm::decode(&b""[..]) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::NoMoreImages => assert!(true),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn probe_reads_only_header() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save_max_val(&[0,0, 15,255], 2, 1, Mode::BINARY, 4095);
+    }
+    let mut reader = &buffer[..];
+    let header = netbpm::probe(&mut reader).unwrap();
+    assert_eq!(ImageType::PGM, header.image_type);
+    assert_eq!(Mode::BINARY, header.mode);
+    assert_eq!(2, header.width);
+    assert_eq!(1, header.height);
+    assert_eq!(4095, header.max_val);
+    assert_eq!(BitDepth::SIXTEEN, header.depth);
+    assert_eq!(buffer.len() - 4, header.dat_start);
+    // the reader is left at the start of the raster.
+    assert_eq!(&[0,0, 15,255], reader);
+}
+
+#[test]
+fn probe_pam() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[0,255, 255,0], 2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA");
+    }
+    let mut reader = &buffer[..];
+    let header = netbpm::probe(&mut reader).unwrap();
+    assert_eq!(ImageType::PAM, header.image_type);
+    assert_eq!(2, header.channels);
+    assert_eq!("GRAYSCALE_ALPHA", header.tuple_type);
+    assert_eq!(4, reader.len());
+}
+
+#[test]
+fn probe_not_netbpm_error() {
+    let mut reader = &b"GIF89a and a lot more data"[..];
+    match netbpm::probe(&mut reader) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::BadMagicNumber{expected} => assert_eq!(expected, None),
+            _ => assert!(false),
+        },
+    }
+    // only the magic number was read.
+    assert_eq!(b"F89a and a lot more data", reader);
+}
+
+#[test]
+fn probe_truncated_error() {
+    match netbpm::probe(&b"P6\n3 2\n25"[..]) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::Truncated => assert!(true),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn probe_comments() {
+    let mut reader = &b"P2 # 1 2 3 4\n3#width\n2 # height 9\n255#max\n1 2 3\n4 5 6\n"[..];
+    let header = netbpm::probe(&mut reader).unwrap();
+    assert_eq!((3, 2, 255), (header.width, header.height, header.max_val));
+    assert_eq!(4, header.comments.len());
+    assert_eq!(b"1 2 3\n4 5 6\n", reader);
+
+    let mut reader = &b"P7\n# ENDHDR\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nENDHDR\n\x07"[..];
+    let header = netbpm::probe(&mut reader).unwrap();
+    assert_eq!(1, header.channels);
+    assert_eq!(b"\x07", reader);
+}
+
+#[test]
+fn probe_long_header() {
+    // Long runs of whitespace are read without parsing the header again for every byte.
+    let mut dat = b"P5".to_vec();
+    dat.extend(vec![b' '; 500000]);
+    dat.extend_from_slice(b"1 1 255\n\x00");
+    let mut reader = &dat[..];
+    assert_eq!(dat.len() - 1, netbpm::probe(&mut reader).unwrap().dat_start);
+
+    let mut dat = b"P5 ".to_vec();
+    dat.extend(vec![b' '; 2000000]);
+    match netbpm::probe(&dat[..]) {
+        Err(Error::LimitExceeded{limit: "header size", ..}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn any_decoder_mixed_formats() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[1,0,0,1], 2, 2, Mode::ASCII);
+    }
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[255,0,0, 0,0,255], 2, 1, Mode::BINARY, BitDepth::EIGHT);
+    }
+    let decoder = netbpm::AnyDecoder::from_reader(&buffer[..]);
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(2, images.len());
+    assert_eq!(ImageType::PBM, images[0].image_type);
+    assert!(test_arrs(4, &images[0].dat, &[1,0,0,1]));
+    assert_eq!(ImageType::PPM, images[1].image_type);
+    assert!(test_arrs(6, &images[1].dat, &[255,0,0, 0,0,255]));
+}
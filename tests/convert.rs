@@ -0,0 +1,105 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::convert::Luminance;
+use netbpm::{BitDepth,Error,ImageType,Mode};
+
+
+fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
+    let mut ret = true;
+    for i in 0..size {
+        ret = ret && (a[i as usize] == b[i as usize]);
+    }
+    ret
+}
+
+#[test]
+fn convert_ppm_to_pgm() {
+    let image = netbpm::decode(&b"P3\n3 1\n255\n255 0 0  0 255 0  0 0 255\n"[..]).unwrap();
+    let gray = image.to_pgm(Luminance::REC601).unwrap();
+    assert_eq!(ImageType::PGM, gray.image_type);
+    assert_eq!(1, gray.channels);
+    assert_eq!(255, gray.max_val);
+    assert!(test_arrs(3, &gray.dat, &[76,150,29]));
+    let gray = image.to_pgm(Luminance::REC709).unwrap();
+    assert!(test_arrs(3, &gray.dat, &[54,182,18]));
+}
+
+#[test]
+fn convert_ppm_to_pgm_16() {
+    let image = netbpm::decode(&b"P3\n1 1\n65535\n65535 65535 65535\n"[..]).unwrap();
+    let gray = image.to_pgm(Luminance::REC709).unwrap();
+    assert_eq!(BitDepth::SIXTEEN, gray.depth);
+    assert!(test_arrs(2, &gray.dat, &[255,255]));
+}
+
+#[test]
+fn convert_pbm_polarity() {
+    let image = netbpm::decode(&b"P1\n2 1\n1 0\n"[..]).unwrap();
+    let gray = image.to_pgm(Luminance::REC601).unwrap();
+    assert_eq!(255, gray.max_val);
+    assert!(test_arrs(2, &gray.dat, &[0,255]));
+    let color = image.to_ppm().unwrap();
+    assert_eq!(3, color.channels);
+    assert!(test_arrs(6, &color.dat, &[0,0,0, 255,255,255]));
+    let bitmap = gray.to_pbm(Luminance::REC601, 0.5).unwrap();
+    assert_eq!(1, bitmap.max_val);
+    assert!(test_arrs(2, &bitmap.dat, &[1,0]));
+}
+
+#[test]
+fn convert_pgm_to_pbm_threshold() {
+    let image = netbpm::decode(&b"P2\n4 1\n15\n0 7 8 15\n"[..]).unwrap();
+    let bitmap = image.to_pbm(Luminance::REC601, 0.5).unwrap();
+    assert_eq!(ImageType::PBM, bitmap.image_type);
+    assert!(test_arrs(4, &bitmap.dat, &[1,1,0,0]));
+    let bitmap = image.to_pbm(Luminance::REC601, 1.0).unwrap();
+    assert!(test_arrs(4, &bitmap.dat, &[1,1,1,0]));
+    match image.to_pbm(Luminance::REC601, 1.5) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn convert_pgm_to_ppm() {
+    let image = netbpm::decode(&b"P5\n2 1\n1000\n\x00\x01\x03\xe8"[..]).unwrap();
+    let color = image.to_ppm().unwrap();
+    assert_eq!(Mode::BINARY, color.mode);
+    assert_eq!(1000, color.max_val);
+    assert!(test_arrs(12, &color.dat, &[0,1,0,1,0,1, 3,232,3,232,3,232]));
+}
+
+#[test]
+fn convert_with_max_val() {
+    let image = netbpm::decode(&b"P2\n3 1\n255\n0 128 255\n"[..]).unwrap();
+    let image = image.with_max_val(65535).unwrap();
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert!(test_arrs(6, &image.dat, &[0,0, 128,128, 255,255]));
+    let image = image.with_max_val(3).unwrap();
+    assert_eq!(BitDepth::EIGHT, image.depth);
+    assert!(test_arrs(3, &image.dat, &[0,2,3]));
+    match image.with_max_val(0) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn convert_keeps_comments() {
+    let image = netbpm::decode(&b"P2\n# note\n1 1\n255\n9\n"[..]).unwrap();
+    let color = image.to_ppm().unwrap();
+    assert_eq!("note", color.comments[0].text);
+}
+
+#[test]
+fn convert_pam_errors() {
+    let image = netbpm::decode(&b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nENDHDR\n\x07"[..]).unwrap();
+    match image.to_ppm() {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+    let image = image.with_max_val(15).unwrap();
+    assert_eq!(ImageType::PAM, image.image_type);
+    assert!(test_arrs(1, &image.dat, &[0]));
+}
@@ -0,0 +1,177 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pam::PAMDecoder;
+use netbpm::pbm::{PBMDecoder,PBMEncoder};
+use netbpm::pgm::{PGMDecoder,PGMEncoder};
+use netbpm::ppm::{PPMDecoder,PPMEncoder};
+use netbpm::{Error,Mode};
+
+#[test]
+fn decode_into_matches_load() {
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        for &max_val in &[255, 1000] {
+            let samples:Vec<u16> = (0..30).map(|i| i * 31 % (max_val + 1)).collect();
+            let mut buffer:Vec<u8> = vec![];
+            {
+                let mut encoder = PPMEncoder::from_writer(&mut buffer);
+                encoder.save_u16(&samples, 5, 2, mode, max_val).unwrap();
+            }
+            let image = PPMDecoder::from_reader(&buffer[..]).load().unwrap();
+            let mut frame = vec![0; image.dat.len()];
+            let header = PPMDecoder::from_reader(&buffer[..]).decode_into(&mut frame).unwrap();
+            assert_eq!(frame, image.dat);
+            assert_eq!(header.width, 5);
+            assert_eq!(header.height, 2);
+            assert_eq!(header.max_val, max_val);
+
+            let mut frame = vec![0; 30];
+            PPMDecoder::from_reader(&buffer[..]).decode_into_u16(&mut frame).unwrap();
+            assert_eq!(frame, samples);
+        }
+    }
+}
+
+#[test]
+fn decode_into_pbm() {
+    let dat:Vec<u8> = (0..33).map(|i| (i % 3 == 0) as u8).collect();
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        let mut buffer:Vec<u8> = vec![];
+        {
+            let mut encoder = PBMEncoder::from_writer(&mut buffer);
+            encoder.save(&dat, 11, 3, mode).unwrap();
+        }
+        let mut frame = vec![0; 33];
+        PBMDecoder::from_reader(&buffer[..]).decode_into(&mut frame).unwrap();
+        assert_eq!(frame, dat);
+    }
+}
+
+#[test]
+fn decode_into_pam() {
+    let data:&[u8] = b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 2\nMAXVAL 65535\nENDHDR\n\x01\x02\x03\x04\x05\x06\x07\x08";
+    let mut frame = [0u8; 8];
+    let header = PAMDecoder::from_reader(data).decode_into(&mut frame).unwrap();
+    assert_eq!(frame, [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(header.channels, 2);
+}
+
+#[test]
+fn decode_into_reuses_buffer() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        for i in 0..10 {
+            encoder.save_max_val(&[i, i + 1, i + 2, i + 3], 2, 2, Mode::BINARY, 255).unwrap();
+        }
+    }
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let mut frame = [0u8; 4];
+    for i in 0..10 {
+        decoder.decode_into(&mut frame).unwrap();
+        assert_eq!(frame, [i, i + 1, i + 2, i + 3]);
+    }
+    match decoder.decode_into(&mut frame) {
+        Err(Error::NoMoreImages) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn decode_into_wrong_size() {
+    let data:&[u8] = b"P5\n2 2\n255\n\x00\x01\x02\x03";
+    let mut frame = [0u8; 3];
+    match PGMDecoder::from_reader(data).decode_into(&mut frame) {
+        Err(Error::DimensionMismatch{expected: 4, found: 3}) => {},
+        _ => assert!(false),
+    }
+    let mut frame = [0u16; 5];
+    match PGMDecoder::from_reader(data).decode_into_u16(&mut frame) {
+        Err(Error::DimensionMismatch{expected: 8, found: 10}) => {},
+        _ => assert!(false),
+    }
+    assert_eq!(frame, [0; 5]);
+}
+
+#[test]
+fn decode_into_wrong_size_then_retry() {
+    let data:&[u8] = b"P2\n2 1\n255\n1 2\nP5\n1 1\n255\n\x03";
+    let mut decoder = PGMDecoder::from_reader(data);
+    let mut frame = [0u8; 1];
+    match decoder.decode_into(&mut frame) {
+        Err(Error::DimensionMismatch{expected: 2, found: 1}) => {},
+        _ => assert!(false),
+    }
+    let mut wide = [0u16; 1];
+    match decoder.decode_into_u16(&mut wide) {
+        Err(Error::DimensionMismatch{expected: 4, found: 2}) => {},
+        _ => assert!(false),
+    }
+    let mut frame = [0u8; 2];
+    decoder.decode_into(&mut frame).unwrap();
+    assert_eq!(frame, [1, 2]);
+    assert_eq!(decoder.load().unwrap().dat, vec![3]);
+
+    let mut decoder = PBMDecoder::from_reader(&b"P4\n8 1\n\xf0P4\n8 1\n\x0f"[..]);
+    let mut frame = [0u8; 4];
+    assert!(decoder.decode_into(&mut frame).is_err());
+    let mut frame = [0u8; 8];
+    decoder.decode_into(&mut frame).unwrap();
+    assert_eq!(frame, [1, 1, 1, 1, 0, 0, 0, 0]);
+}
+
+#[test]
+fn decode_into_wrong_format() {
+    let mut frame = [0u8; 1];
+    match PPMDecoder::from_reader(&b"P5\n1 1\n255\n\x00"[..]).decode_into(&mut frame) {
+        Err(Error::BadMagicNumber{expected: Some("ppm")}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn decode_into_truncated() {
+    let mut frame = [0u8; 4];
+    match PGMDecoder::from_reader(&b"P5\n2 2\n255\n\x00\x01\x02"[..]).decode_into(&mut frame) {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+    let mut frame = [0u16; 4];
+    match PGMDecoder::from_reader(&b"P2\n2 2\n255\n0 1 2\n"[..]).decode_into_u16(&mut frame) {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+    let mut frame = [0u8; 16];
+    match PBMDecoder::from_reader(&b"P4\n8 2\n\xff"[..]).decode_into(&mut frame) {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn decode_into_sample_out_of_range() {
+    let mut frame = [0u16; 2];
+    match PGMDecoder::from_reader(&b"P5\n2 1\n100\n\x01\xc8"[..]).decode_into_u16(&mut frame) {
+        Err(Error::SampleOutOfRange{value: 200, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+    let mut frame = [0u8; 2];
+    match PGMDecoder::from_reader(&b"P2\n2 1\n100\n1 200\n"[..]).decode_into(&mut frame) {
+        Err(Error::SampleOutOfRange{value: 200, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn decode_into_u16_large_image() {
+    // Larger than the block used to read binary samples.
+    let samples:Vec<u16> = (0..20000).map(|i| (i * 7 % 65536) as u16).collect();
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        encoder.save_u16(&samples, 200, 100, Mode::BINARY, 65535).unwrap();
+    }
+    let mut frame = vec![0u16; 20000];
+    PGMDecoder::from_reader(&buffer[..]).decode_into_u16(&mut frame).unwrap();
+    assert_eq!(frame, samples);
+}
@@ -0,0 +1,177 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pbm::PBMDecoder;
+use netbpm::pgm::PGMDecoder;
+use netbpm::ppm::PPMDecoder;
+use netbpm::{Error,Warning};
+
+#[test]
+fn strict_raster_character() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n255\n1 2x 3\n"[..]);
+    match decoder.load() {
+        Err(Error::UnexpectedRasterCharacter{character: b'x', offset: 3}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n3 1\n1 2 0\n"[..]);
+    match decoder.load() {
+        Err(Error::UnexpectedRasterCharacter{character: b'2', offset: 2}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PPMDecoder::from_reader(&b"P3\n1 1\n255\n1,2,3\n"[..]);
+    match decoder.load_partial() {
+        Err(Error::UnexpectedRasterCharacter{character: b',', offset: 1}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn strict_raster_comments() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n255\n1 # first\n2#second\r3\n"[..]);
+    assert_eq!(vec![1, 2, 3], decoder.load().unwrap().dat);
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n3 1\n1#one\n01\n"[..]);
+    assert_eq!(vec![1, 0, 1], decoder.load().unwrap().dat);
+}
+
+#[test]
+fn strict_sample_out_of_range() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n2 1\n100\n100 300\n"[..]);
+    match decoder.load() {
+        Err(Error::SampleOutOfRange{value: 300, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 1\n100\n\x64\x65"[..]);
+    match decoder.load() {
+        Err(Error::SampleOutOfRange{value: 101, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PPMDecoder::from_reader(&b"P6\n1 1\n1000\n\x03\xe8\x03\xe9\x00\x00"[..]);
+    match decoder.load() {
+        Err(Error::SampleOutOfRange{value: 1001, max_val: 1000}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn strict_sample_too_large_for_u16() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n2 1\n65535\n65535 70000\n"[..]);
+    match decoder.load() {
+        Err(Error::SampleOutOfRange{value: 70000, max_val: 65535}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n1 1\n65535\n99999999999\n"[..]);
+    match decoder.load() {
+        Err(Error::SampleOutOfRange{value: 4294967295, max_val: 65535}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n2 1\n65535\n65535 70000\n"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![255, 255, 255, 255], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::SampleAboveMaxVal{count: 1}]);
+}
+
+#[test]
+fn strict_rows() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n2 1\n255\n1 ? 2\n"[..]);
+    let mut rows = decoder.rows().unwrap();
+    let mut row = vec![0; 2];
+    match rows.read_row(&mut row) {
+        Err(Error::UnexpectedRasterCharacter{character: b'?', offset: 2}) => {},
+        _ => assert!(false),
+    }
+
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 2\n100\n\x01\x02\x03\x65"[..]);
+    let mut rows = decoder.rows().unwrap();
+    let mut row = vec![0; 2];
+    assert!(rows.read_row(&mut row).unwrap());
+    match rows.read_row(&mut row) {
+        Err(Error::SampleOutOfRange{value: 101, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PPMDecoder::from_reader(&b"P6\n1 1\n1000\n\x03\xe8\x03\xe9\x00\x00"[..]);
+    match decoder.rows().unwrap().next() {
+        Some(Err(Error::SampleOutOfRange{value: 1001, max_val: 1000})) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn lenient_valid_file() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 1\n255\n\x00\xff"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![0, 255], lenient.image.dat);
+    assert!(lenient.warnings.is_empty());
+}
+
+#[test]
+fn lenient_raster_characters() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n255\n1 ,2; 3.\n"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 2, 3], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::UnexpectedCharacters{count: 3, offset: 2}]);
+
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n4 1\n1 2 0 a1 1\n"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 0, 1, 1], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::UnexpectedCharacters{count: 2, offset: 2}]);
+}
+
+#[test]
+fn lenient_sample_above_max_val() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n100\n150 50 70000\n"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![100, 50, 100], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::SampleAboveMaxVal{count: 2}]);
+
+    let mut decoder = PPMDecoder::from_reader(&b"P6\n1 1\n1000\n\x03\xe8\xff\xff\x00\x01"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![3, 232, 3, 232, 0, 1], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::SampleAboveMaxVal{count: 1}]);
+}
+
+#[test]
+fn lenient_truncated() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 2\n255\n\x01\x02\x03"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 2, 3, 0], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::Truncated{rows: 1}]);
+
+    let mut decoder = PBMDecoder::from_reader(&b"P4\n8 2\n\xff"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::Truncated{rows: 1}]);
+}
+
+#[test]
+fn lenient_trailing_data() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 1\n255\n\x01\x02\n\ngarbage\x00"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 2], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::TrailingData{bytes: 8}]);
+    match decoder.load() {
+        Err(Error::NoMoreImages) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn lenient_keeps_next_image() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n1 1\n255\n7\nP2\n1 1\n255\n9\n"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![7], lenient.image.dat);
+    assert!(lenient.warnings.is_empty());
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![9], lenient.image.dat);
+    assert!(lenient.warnings.is_empty());
+}
+
+#[test]
+fn lenient_several_warnings() {
+    let mut decoder = PPMDecoder::from_reader(&b"P3\n2 1\n15\n1 x 20 3"[..]);
+    let lenient = decoder.load_lenient().unwrap();
+    assert_eq!(vec![1, 15, 3, 0, 0, 0], lenient.image.dat);
+    assert_eq!(lenient.warnings, vec![Warning::UnexpectedCharacters{count: 1, offset: 2},
+                                      Warning::SampleAboveMaxVal{count: 1},
+                                      Warning::Truncated{rows: 0}]);
+    assert_eq!(lenient.warnings[2].to_string(), "File ended before the end of the image after 0 rows.");
+}
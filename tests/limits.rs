@@ -0,0 +1,103 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pam::PAMDecoder;
+use netbpm::pbm::PBMDecoder;
+use netbpm::pfm::PFMDecoder;
+use netbpm::pgm::PGMDecoder;
+use netbpm::ppm::PPMDecoder;
+use netbpm::{AnyDecoder,Error,Limits};
+
+#[test]
+fn limits_default_allows_images() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 1\n255\n\x00\xff"[..]);
+    decoder.set_limits(Limits::default());
+    assert_eq!(vec![0, 255], decoder.load().unwrap().dat);
+}
+
+#[test]
+fn limits_width_and_height() {
+    let limits = Limits{max_width: 2, max_height: 3, ..Limits::default()};
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n3 1\n255\n\x00\x00\x00"[..]);
+    decoder.set_limits(limits);
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "width", value: 3, max: 2}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n1 4\n0 0 0 0\n"[..]);
+    decoder.set_limits(limits);
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "height", value: 4, max: 3}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n2 3\n0 0 0 0 0 0\n"[..]);
+    decoder.set_limits(limits);
+    assert!(decoder.load().is_ok());
+}
+
+#[test]
+fn limits_pixels() {
+    let mut decoder = PPMDecoder::from_reader(&b"P6\n100000 100000\n255\n"[..]);
+    decoder.set_limits(Limits{max_pixels: 1_000_000, ..Limits::default()});
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "pixel count", value: 10_000_000_000, max: 1_000_000}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn limits_allocation() {
+    // Sixteen-bit samples with three channels take six bytes for each pixel.
+    let mut decoder = PPMDecoder::from_reader(&b"P6\n10 10\n65535\n"[..]);
+    decoder.set_limits(Limits{max_alloc: 599, ..Limits::default()});
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "allocation", value: 600, max: 599}) => {},
+        _ => assert!(false),
+    }
+    let mut decoder = PAMDecoder::from_reader(&b"P7\nWIDTH 4294967295\nHEIGHT 4294967295\nDEPTH 4294967295\nMAXVAL 65535\nENDHDR\n"[..]);
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "allocation", ..}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn limits_row_allocation() {
+    // An image with no rows can still have rows too long to allocate.
+    let mut decoder = PAMDecoder::from_reader(&b"P7\nWIDTH 4294967295\nHEIGHT 0\nDEPTH 4294967295\nMAXVAL 65535\nENDHDR\n"[..]);
+    match decoder.rows() {
+        Err(Error::LimitExceeded{limit: "allocation", ..}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn limits_header_size() {
+    let mut dat = b"P2\n# ".to_vec();
+    dat.extend(vec![b'a'; 100_000]);
+    let mut decoder = AnyDecoder::from_reader(&dat[..]);
+    decoder.set_limits(Limits{max_alloc: 1000, ..Limits::default()});
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "header size", ..}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn limits_pfm() {
+    let mut decoder = PFMDecoder::from_reader(&b"PF\n10 10\n-1.0\n"[..]);
+    decoder.set_limits(Limits{max_alloc: 1000, ..Limits::default()});
+    match decoder.load() {
+        Err(Error::LimitExceeded{limit: "allocation", value: 1200, max: 1000}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn limits_overflowing_header() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n18446744073709551617 1\n255\n"[..]);
+    match decoder.load() {
+        Err(Error::InvalidHeader(_)) => {},
+        _ => assert!(false),
+    }
+}
@@ -0,0 +1,221 @@
+#![allow(clippy::assertions_on_constants)]
+
+// A corpus of malformed and truncated files. Every decoder must return an error for these rather
+// than panicking or returning an image with the wrong amount of data.
+
+extern crate netbpm;
+use netbpm::pam::PAMDecoder;
+use netbpm::pbm::PBMDecoder;
+use netbpm::pfm::PFMDecoder;
+use netbpm::pgm::PGMDecoder;
+use netbpm::ppm::PPMDecoder;
+use netbpm::{AnyDecoder,Error,Limits};
+
+/// Well formed files that the corpus is made from, along with the length of their header.
+const VALID:[(&[u8], usize); 10] = [
+    (b"P1\n3 2\n1 0 1\n0 1 0\n", 7),
+    (b"P4\n10 2\n\xff\xc0\x00\x40", 8),
+    (b"P2\n# comment\n3 2\n255\n0 128 255\n255 128 0\n", 21),
+    (b"P5\n3 2\n255\n\x00\x80\xff\xff\x80\x00", 11),
+    (b"P5\n2 1\n65535\n\x12\x34\xff\xff", 13),
+    (b"P3\n2 1\n255\n255 0 0 0 0 255\n", 11),
+    (b"P6\n2 1\n255\n\xff\x00\x00\x00\x00\xff", 11),
+    (b"P6\n1 1\n1000\n\x03\xe8\x00\x00\x00\x01", 12),
+    (b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\nTUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n\x00\xff\xff\x80", 71),
+    (b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 65535\nENDHDR\n\x01\x02", 48),
+];
+
+/// Files that are broken in ways other than being cut short.
+const MALFORMED:[&[u8]; 28] = [
+    b"",
+    b"P",
+    b"Q",
+    b"P0",
+    b"P8\n1 1\n",
+    b"\x00\x00\x00\x00",
+    b"P2",
+    b"P2\n",
+    b"P2\n-1 1\n255\n0\n",
+    b"P2\n1 1\n0\n0\n",
+    b"P2\n1 1\n65536\n0\n",
+    b"P2\n1 1\n99999999999999999999\n0\n",
+    b"P5\n99999999999 1\n255\n",
+    b"P5\n4294967295 4294967295\n65535\n\x00",
+    b"P4\n4294967295 4294967295\n\x00",
+    b"P1\n1 x\n1\n",
+    b"P2\n1 1\n2x5\n0\n",
+    b"P2 #\n",
+    b"P2\n# comment that never ends",
+    b"P7",
+    b"P7 WIDTH 1",
+    b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\n",
+    b"P7\nWIDTH x\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nENDHDR\n\x00",
+    b"P7\nWIDTH 1\nHEIGHT 1\nMAXVAL 255\nENDHDR\n\x00",
+    b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 0\nENDHDR\n\x00",
+    b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 255\nBOGUS 1\nENDHDR\n\x00",
+    b"P7\nWIDTH 4294967295\nHEIGHT 4294967295\nDEPTH 4294967295\nMAXVAL 65535\nENDHDR\n",
+    b"P7\nWIDTH 4294967295\nHEIGHT 0\nDEPTH 4294967295\nMAXVAL 65535\nENDHDR\n",
+];
+
+/// Runs every decoder over `dat`, checking that none of them panic and that any image they load
+/// has the amount of data its size calls for.
+fn decode_all(dat: &[u8]) -> Vec<Result<usize, Error>> {
+    let mut results = vec![];
+    let bytes = |depth: netbpm::BitDepth| if depth == netbpm::BitDepth::SIXTEEN { 2 } else { 1 };
+
+    let result = PBMDecoder::from_reader(dat).load().map(|i| {
+        assert_eq!((i.width * i.height) as usize, i.dat.len());
+        i.dat.len()
+    });
+    results.push(result);
+    for result in [PGMDecoder::from_reader(dat).load(), PGMDecoder::from_reader(dat).load_rescaled()] {
+        results.push(result.map(|i| {
+            assert_eq!((i.width * i.height) as usize * bytes(i.depth), i.dat.len());
+            i.dat.len()
+        }));
+    }
+    results.push(PGMDecoder::from_reader(dat).load_u16().map(|i| i.dat.len()));
+    for result in [PPMDecoder::from_reader(dat).load(), PPMDecoder::from_reader(dat).load_rescaled()] {
+        results.push(result.map(|i| {
+            assert_eq!((i.width * i.height) as usize * 3 * bytes(i.depth), i.dat.len());
+            i.dat.len()
+        }));
+    }
+    results.push(PPMDecoder::from_reader(dat).load_u16().map(|i| i.dat.len()));
+    let result = PAMDecoder::from_reader(dat).load().map(|i| {
+        assert_eq!((i.width * i.height * i.channels) as usize * bytes(i.depth), i.dat.len());
+        i.dat.len()
+    });
+    results.push(result);
+    let result = AnyDecoder::from_reader(dat).load().map(|i| {
+        let expected = (i.width * i.height * i.channels) as usize;
+        assert_eq!(expected * bytes(i.depth), i.dat.len());
+        i.dat.len()
+    });
+    results.push(result);
+    // Partial images are loaded from truncated files, so only their size is checked.
+    let _ = AnyDecoder::from_reader(dat).load_partial().map(|p| {
+        let i = p.image;
+        assert_eq!((i.width * i.height * i.channels) as usize * bytes(i.depth), i.dat.len());
+        assert!(p.rows <= i.height);
+        assert_eq!(p.complete, p.rows == i.height);
+    });
+    results.push(PFMDecoder::from_reader(dat).load().map(|i| i.dat.len()));
+    let _ = netbpm::probe(dat);
+
+    // Read the rows one at a time as well.
+    let mut decoder = AnyDecoder::from_reader(dat);
+    let result = decoder.rows().and_then(|rows| {
+        let mut count = 0;
+        for row in rows {
+            count += row?.len();
+        }
+        Ok(count)
+    });
+    results.push(result);
+    results
+}
+
+#[test]
+fn malformed_valid_files_load() {
+    for &(dat, _) in VALID.iter() {
+        let mut decoder = AnyDecoder::from_reader(dat);
+        let image = decoder.load().unwrap();
+        assert!(decoder.next().is_none());
+        let partial = AnyDecoder::from_reader(dat).load_partial().unwrap();
+        assert!(partial.complete);
+        assert_eq!(image.dat, partial.image.dat);
+    }
+}
+
+#[test]
+fn malformed_corpus() {
+    for &dat in MALFORMED.iter() {
+        for (k, result) in decode_all(dat).into_iter().enumerate() {
+            assert!(result.is_err(), "{:?} was loaded by {} {:?}", String::from_utf8_lossy(dat), k, result);
+        }
+    }
+}
+
+#[test]
+fn malformed_truncated_raster() {
+    for &(dat, header_len) in VALID.iter() {
+        for len in header_len..dat.len() {
+            // Cutting a plain raster part way through the last sample leaves a shorter sample
+            // that can't be told apart from a complete one.
+            let rest = &dat[len..];
+            if dat[len - 1].is_ascii_digit() && !rest.iter().skip_while(|c| c.is_ascii_digit()).any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            match AnyDecoder::from_reader(&dat[..len]).load() {
+                Err(Error::Truncated) => {},
+                _ => assert!(false, "{:?} wasn't truncated", String::from_utf8_lossy(&dat[..len])),
+            }
+            let partial = AnyDecoder::from_reader(&dat[..len]).load_partial().unwrap();
+            assert!(!partial.complete);
+            for (k, result) in decode_all(&dat[..len]).into_iter().enumerate() {
+                assert!(result.is_err(), "{:?} was loaded by {} {:?}", String::from_utf8_lossy(&dat[..len]), k, result);
+            }
+        }
+    }
+}
+
+#[test]
+fn malformed_truncated_header() {
+    for &(dat, header_len) in VALID.iter() {
+        for len in 0..header_len {
+            for (k, result) in decode_all(&dat[..len]).into_iter().enumerate() {
+                assert!(result.is_err(), "{:?} was loaded by {} {:?}", String::from_utf8_lossy(&dat[..len]), k, result);
+            }
+        }
+    }
+}
+
+#[test]
+fn malformed_partial_rows() {
+    let partial = PGMDecoder::from_reader(&b"P5\n3 3\n255\n\x01\x02\x03\x04"[..]).load_partial().unwrap();
+    assert!(!partial.complete);
+    assert_eq!(1, partial.rows);
+    assert_eq!(vec![1, 2, 3, 4, 0, 0, 0, 0, 0], partial.image.dat);
+    let partial = PBMDecoder::from_reader(&b"P4\n10 2\n\xff"[..]).load_partial().unwrap();
+    assert_eq!(0, partial.rows);
+    assert_eq!(20, partial.image.dat.len());
+    let partial = PPMDecoder::from_reader(&b"P3\n1 2\n255\n1 2 3\n4"[..]).load_partial().unwrap();
+    assert_eq!(1, partial.rows);
+    assert_eq!(vec![1, 2, 3, 4, 0, 0], partial.image.dat);
+    let partial = PAMDecoder::from_reader(&b"P7\nWIDTH 1\nHEIGHT 2\nDEPTH 1\nMAXVAL 255\nENDHDR\n\x07\x08"[..]).load_partial().unwrap();
+    assert!(partial.complete);
+    assert_eq!(2, partial.rows);
+}
+
+#[test]
+fn malformed_mutations() {
+    // Flip bytes of the valid files with a simple generator so the same cases run every time.
+    let mut seed:u32 = 12345;
+    let mut next = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        seed >> 8
+    };
+    for &(dat, _) in VALID.iter() {
+        for _ in 0..300 {
+            let mut mutated = dat.to_vec();
+            for _ in 0..1 + next() % 3 {
+                let i = next() as usize % mutated.len();
+                mutated[i] = next() as u8;
+            }
+            let _ = decode_all(&mutated);
+        }
+    }
+}
+
+#[test]
+fn malformed_limits() {
+    for &(dat, _) in VALID.iter() {
+        let mut decoder = AnyDecoder::from_reader(dat);
+        decoder.set_limits(Limits{max_alloc: 1, ..Limits::default()});
+        match decoder.load() {
+            Err(Error::LimitExceeded{..}) => {},
+            _ => assert!(false),
+        }
+    }
+}
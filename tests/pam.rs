@@ -0,0 +1,247 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pam::{PAMEncoder,PAMDecoder};
+use netbpm::{BitDepth,Comment,Error};
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+
+// 3 2, RGB_ALPHA
+const RGBA:[u8;24] = [255,0,0,255,     0,255,0,255,     0,0,255,255,
+                      255,255,255,0,   0,0,0,128,       128,128,128,64];
+
+// 3 2, GRAYSCALE_ALPHA
+const GA:[u8;12] = [0,255,   128,255,   255,255,
+                    0,0,     128,0,     255,0];
+
+// 2 2, GRAYSCALE_ALPHA
+const GA_DOUBLE:[u8;16] = [0,0,255,255,       255,255,255,255,
+                           18,52,0,0,         255,255,128,0];
+
+fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
+    let mut ret = true;
+    for i in 0..size {
+        ret = ret && (a[i as usize] == b[i as usize]);
+    }
+    ret
+}
+
+#[test]
+fn pam_rgba() {
+    let mut encoder = PAMEncoder::new("test_0.pam").unwrap();
+    let enc_result = encoder.save(&RGBA, 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA");
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PAMDecoder::new("test_0.pam").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(3, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(4, image.channels);
+    assert_eq!(BitDepth::EIGHT, image.depth);
+    assert_eq!("RGB_ALPHA", image.tuple_type);
+    assert_eq!(24, image.dat.len());
+    assert!(test_arrs(24, &image.dat, &RGBA));
+    let _ = fs::remove_file("test_0.pam");
+}
+
+#[test]
+fn pam_gray_alpha() {
+    let mut encoder = PAMEncoder::new("test_1.pam").unwrap();
+    let enc_result = encoder.save(&GA, 3, 2, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA");
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PAMDecoder::new("test_1.pam").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(3, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(2, image.channels);
+    assert_eq!("GRAYSCALE_ALPHA", image.tuple_type);
+    assert!(test_arrs(12, &image.dat, &GA));
+    let _ = fs::remove_file("test_1.pam");
+}
+
+#[test]
+fn pam_gray_alpha_double() {
+    let mut encoder = PAMEncoder::new("test_2.pam").unwrap();
+    let enc_result = encoder.save(&GA_DOUBLE, 2, 2, 2, BitDepth::SIXTEEN, "GRAYSCALE_ALPHA");
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PAMDecoder::new("test_2.pam").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(2, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(2, image.channels);
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert!(test_arrs(16, &image.dat, &GA_DOUBLE));
+    let _ = fs::remove_file("test_2.pam");
+}
+
+#[test]
+fn pam_no_tuple_type() {
+    let mut encoder = PAMEncoder::new("test_3.pam").unwrap();
+    let enc_result = encoder.save(&GA, 6, 1, 2, BitDepth::EIGHT, "");
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut contents = String::new();
+    let _ = File::open("test_3.pam").unwrap().read_to_string(&mut contents);
+    assert!(!contents.contains("TUPLTYPE"));
+    let mut decoder = PAMDecoder::new("test_3.pam").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(6, image.width);
+    assert_eq!(1, image.height);
+    assert_eq!(2, image.channels);
+    assert_eq!("", image.tuple_type);
+    let _ = fs::remove_file("test_3.pam");
+}
+
+#[test]
+fn pam_zero_channels_error() {
+    let mut encoder = PAMEncoder::new("test_4.pam").unwrap();
+    let enc_result = encoder.save(&GA, 6, 2, 0, BitDepth::EIGHT, "");
+    match enc_result {
+        Ok(()) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"Pam images must have at least one channel."),
+    }
+    let _ = fs::remove_file("test_4.pam");
+}
+
+#[test]
+fn pam_wrong_filetype_error() {
+    let mut file = File::create("file_type.pam").unwrap();
+    let _ = file.write(b"P2\n2 2\n255\n0 255\n255 0");
+    let mut decoder = PAMDecoder::new("file_type.pam").unwrap();
+    let dec_result = decoder.load();
+    match dec_result {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"Input file is not a pam file."),
+    }
+    let _ = fs::remove_file("file_type.pam");
+}
+
+#[test]
+fn pam_multiple_images() {
+    let mut encoder = PAMEncoder::new("test_5.pam").unwrap();
+    let _ = encoder.save(&RGBA, 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA");
+    let _ = encoder.save(&GA_DOUBLE, 2, 2, 2, BitDepth::SIXTEEN, "GRAYSCALE_ALPHA");
+    let mut encoder = PAMEncoder::append("test_5.pam").unwrap();
+    let _ = encoder.save(&GA, 3, 2, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA");
+    let decoder = PAMDecoder::new("test_5.pam").unwrap();
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(3, images.len());
+    assert_eq!(4, images[0].channels);
+    assert!(test_arrs(24, &images[0].dat, &RGBA));
+    assert_eq!(16, images[1].dat.len());
+    assert!(test_arrs(16, &images[1].dat, &GA_DOUBLE));
+    assert_eq!(12, images[2].dat.len());
+    assert!(test_arrs(12, &images[2].dat, &GA));
+    let _ = fs::remove_file("test_5.pam");
+}
+
+#[test]
+fn pam_in_memory() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&RGBA, 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA");
+    }
+    let mut decoder = PAMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(4, image.channels);
+    assert!(test_arrs(24, &image.dat, &RGBA));
+}
+
+#[test]
+fn pam_rows() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&RGBA, 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA");
+    }
+    let mut decoder = PAMDecoder::from_reader(&buffer[..]);
+    let mut rows = decoder.rows().unwrap();
+    assert_eq!(4, rows.channels());
+    assert_eq!(12, rows.row_len());
+    assert!(test_arrs(12, &rows.next().unwrap().unwrap(), &RGBA));
+    assert!(test_arrs(12, &rows.next().unwrap().unwrap(), &RGBA[12..]));
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn pam_row_writer() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let mut writer = encoder.rows(2, 2, 2, BitDepth::SIXTEEN, "GRAYSCALE_ALPHA").unwrap();
+        writer.write_row(&GA_DOUBLE[..8]).unwrap();
+        writer.write_row(&GA_DOUBLE[8..]).unwrap();
+        writer.finish().unwrap();
+    }
+    let mut decoder = PAMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!("GRAYSCALE_ALPHA", image.tuple_type);
+    assert!(test_arrs(16, &image.dat, &GA_DOUBLE));
+}
+
+#[test]
+fn pam_comments() {
+    let comments = [Comment{text: "made by the test suite".to_string(), position: 0},
+                    Comment{text: "after tuple type".to_string(), position: 5}];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        encoder.set_comments(&comments);
+        let _ = encoder.save(&RGBA, 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA");
+    }
+    let mut decoder = PAMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(comments.to_vec(), image.comments);
+    assert_eq!("RGB_ALPHA", image.tuple_type);
+    assert!(test_arrs(24, &image.dat, &RGBA));
+}
+
+#[test]
+fn pam_save_wrong_len() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PAMEncoder::from_writer(&mut buffer);
+    match encoder.save(&RGBA[..20], 3, 2, 4, BitDepth::EIGHT, "RGB_ALPHA") {
+        Err(Error::DimensionMismatch{expected: 24, found: 20}) => {},
+        _ => assert!(false),
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn pam_save_sample_over_max_val() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PAMEncoder::from_writer(&mut buffer);
+    match encoder.save_max_val(&[0,1, 1,2], 2, 1, 2, 1, "BLACKANDWHITE_ALPHA") {
+        Err(Error::SampleOutOfRange{value: 2, max_val: 1}) => {},
+        _ => assert!(false),
+    }
+    match encoder.save_stride(&RGBA, 2, 2, 12, 4, 254, "RGB_ALPHA") {
+        Err(Error::SampleOutOfRange{value: 255, max_val: 254}) => {},
+        _ => assert!(false),
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn pam_save_stride() {
+    // The first two pixels of each row of RGBA.
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        encoder.save_stride(&RGBA, 2, 2, 12, 4, 255, "RGB_ALPHA").unwrap();
+    }
+    let image = PAMDecoder::from_reader(&buffer[..]).load().unwrap();
+    assert!(test_arrs(16, &image.dat, &[255,0,0,255, 0,255,0,255, 255,255,255,0, 0,0,0,128]));
+}
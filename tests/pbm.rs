@@ -1,8 +1,9 @@
+#![allow(clippy::assertions_on_constants)]
+
 extern crate netbpm;
 use netbpm::pbm::{PBMEncoder,PBMDecoder};
-use netbpm::Mode;
+use netbpm::{Comment,Error,Mode};
 use std::fs;
-use std::error::Error;
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -58,13 +59,13 @@ pub fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
 
 #[test]
 fn pbm_j_ascii() {
-    let mut encoder = PBMEncoder::new("test_0a.pbm");
+    let mut encoder = PBMEncoder::new("test_0a.pbm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::ASCII);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_0a.pbm");
+    let mut decoder = PBMDecoder::new("test_0a.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -74,13 +75,13 @@ fn pbm_j_ascii() {
 
 #[test]
 fn pbm_j_binary() {
-    let mut encoder = PBMEncoder::new("test_0b.pbm");
+    let mut encoder = PBMEncoder::new("test_0b.pbm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::BINARY);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_0b.pbm");
+    let mut decoder = PBMDecoder::new("test_0b.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -88,15 +89,27 @@ fn pbm_j_binary() {
     let _ = fs::remove_file("test_0b.pbm");
 }
 
+#[test]
+fn pbm_binary_layout() {
+    let dat:[u8;20] = [1,0,0,0,0,0,0,1, 1,1,
+                       0,1,1,1,1,1,1,0, 0,1];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        encoder.save(&dat, 10, 2, Mode::BINARY).unwrap();
+    }
+    assert_eq!(b"P4\n10 2\n\x81\xc0\x7e\x40".to_vec(), buffer);
+}
+
 #[test]
 fn pbm_f_ascii() {
-    let mut encoder = PBMEncoder::new("test_1a.pbm");
+    let mut encoder = PBMEncoder::new("test_1a.pbm").unwrap();
     let enc_result = encoder.save(&F, 10, 9, Mode::ASCII);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_1a.pbm");
+    let mut decoder = PBMDecoder::new("test_1a.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(10, image.width);
     assert_eq!(9, image.height);
@@ -106,13 +119,13 @@ fn pbm_f_ascii() {
 
 #[test]
 fn pbm_f_binary() {
-    let mut encoder = PBMEncoder::new("test_1b.pbm");
+    let mut encoder = PBMEncoder::new("test_1b.pbm").unwrap();
     let enc_result = encoder.save(&F, 10, 9, Mode::BINARY);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_1b.pbm");
+    let mut decoder = PBMDecoder::new("test_1b.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(10, image.width);
     assert_eq!(9, image.height);
@@ -122,13 +135,13 @@ fn pbm_f_binary() {
 
 #[test]
 fn pbm_h_ascii() {
-    let mut encoder = PBMEncoder::new("test_2a.pbm");
+    let mut encoder = PBMEncoder::new("test_2a.pbm").unwrap();
     let enc_result = encoder.save(&H, 16, 14, Mode::ASCII);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_2a.pbm");
+    let mut decoder = PBMDecoder::new("test_2a.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(16, image.width);
     assert_eq!(14, image.height);
@@ -138,13 +151,13 @@ fn pbm_h_ascii() {
 
 #[test]
 fn pbm_h_binary() {
-    let mut encoder = PBMEncoder::new("test_2b.pbm");
+    let mut encoder = PBMEncoder::new("test_2b.pbm").unwrap();
     let enc_result = encoder.save(&H, 16, 14, Mode::BINARY);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PBMDecoder::new("test_2b.pbm");
+    let mut decoder = PBMDecoder::new("test_2b.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(16, image.width);
     assert_eq!(14, image.height);
@@ -153,13 +166,25 @@ fn pbm_h_binary() {
 }
 
 #[test]
-fn pbm_ascii_width_oversize() {
-    let mut encoder = PBMEncoder::new("test_3.pbm");
-    let enc_result = encoder.save(&H, 160, 14, Mode::ASCII);
+fn pbm_ascii_wide() {
+    let mut dat = [0u8;320];
+    for (i, v) in dat.iter_mut().enumerate() {
+        *v = (i % 3 == 0) as u8;
+    }
+    let mut encoder = PBMEncoder::new("test_3.pbm").unwrap();
+    let enc_result = encoder.save(&dat, 160, 2, Mode::ASCII);
     match enc_result {
-        Ok(()) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Width can not be greater than 70 for ascii pbm files."),
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
     }
+    let mut contents = String::new();
+    let _ = File::open("test_3.pbm").unwrap().read_to_string(&mut contents);
+    assert!(contents.lines().all(|l| l.len() <= 70));
+    let mut decoder = PBMDecoder::new("test_3.pbm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(160, image.width);
+    assert_eq!(2, image.height);
+    assert!(test_arrs(320, &image.dat, &dat));
     let _ = fs::remove_file("test_3.pbm");
 }
 
@@ -167,7 +192,7 @@ fn pbm_ascii_width_oversize() {
 fn pbm_ascii_commented() {
     let mut file = File::create("test_4.pbm").unwrap();
     let _ = file.write_fmt(format_args!("P1\n#This is a comment.\n{} {}\n1 0\n0 1",2,2));
-    let mut decoder = PBMDecoder::new("test_4.pbm");
+    let mut decoder = PBMDecoder::new("test_4.pbm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(2, image.width);
     assert_eq!(2, image.height);
@@ -180,11 +205,11 @@ fn pbm_ascii_commented() {
 fn pbm_not_netbpm_file() {
     let mut file = File::create("test_5.pbm").unwrap();
     let _ = file.write_fmt(format_args!("M1\n#This is a comment.\n{} {}\n1 0\n0 1",2,2));
-    let mut decoder = PBMDecoder::new("test_5.pbm");
+    let mut decoder = PBMDecoder::new("test_5.pbm").unwrap();
     let dec_result = decoder.load();
     match dec_result {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Input file is not a netbpm file."),
+        Err(e) => assert_eq!(e.to_string(),"Input file is not a netbpm file."),
     }
     let _ = fs::remove_file("test_5.pbm");
 }
@@ -193,11 +218,11 @@ fn pbm_not_netbpm_file() {
 fn pbm_not_pbm_file() {
     let mut file = File::create("test_6.pbm").unwrap();
     let _ = file.write_fmt(format_args!("P9\n#This is a comment.\n{} {}\n1 0\n0 1",2,2));
-    let mut decoder = PBMDecoder::new("test_6.pbm");
+    let mut decoder = PBMDecoder::new("test_6.pbm").unwrap();
     let dec_result = decoder.load();
     match dec_result {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Input file is not a netbpm file."),
+        Err(e) => assert_eq!(e.to_string(),"Input file is not a netbpm file."),
     }
     let _ = fs::remove_file("test_6.pbm");
 }
@@ -206,11 +231,177 @@ fn pbm_not_pbm_file() {
 fn pbm_bad_header() {
     let mut file = File::create("test_7.pbm").unwrap();
     let _ = file.write_fmt(format_args!("P1\n#This is a comment.\n{} b{}\n1 0\n0 1",2,2));
-    let mut decoder = PBMDecoder::new("test_7.pbm");
+    let mut decoder = PBMDecoder::new("test_7.pbm").unwrap();
     let dec_result = decoder.load();
     match dec_result {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Unexpected character in file header. Character: 98"),
+        Err(e) => assert_eq!(e.to_string(),"Unexpected character in file header at line 3, column 3 (byte 25). Character: 98"),
     }
     let _ = fs::remove_file("test_7.pbm");
 }
+
+#[test]
+fn pbm_multiple_images() {
+    let mut encoder = PBMEncoder::new("test_8.pbm").unwrap();
+    let _ = encoder.save(&J, 6, 10, Mode::BINARY);
+    let _ = encoder.save(&F, 10, 9, Mode::ASCII);
+    let _ = encoder.save(&H, 16, 14, Mode::BINARY);
+    let decoder = PBMDecoder::new("test_8.pbm").unwrap();
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(3, images.len());
+    assert_eq!(60, images[0].dat.len());
+    assert!(test_arrs(60, &images[0].dat, &J));
+    assert_eq!(90, images[1].dat.len());
+    assert!(test_arrs(90, &images[1].dat, &F));
+    assert_eq!(224, images[2].dat.len());
+    assert!(test_arrs(224, &images[2].dat, &H));
+    let _ = fs::remove_file("test_8.pbm");
+}
+
+#[test]
+fn pbm_ascii_packed_images() {
+    // Plain pbm pixels don't need to be separated by whitespace.
+    let mut file = File::create("test_9.pbm").unwrap();
+    let _ = file.write(b"P1\n2 2\n1001P1\n2 1\n01\n");
+    let mut decoder = PBMDecoder::new("test_9.pbm").unwrap();
+    let image = decoder.load().unwrap();
+    assert!(test_arrs(4, &image.dat, &[1, 0, 0, 1]));
+    let image = decoder.load().unwrap();
+    assert_eq!(1, image.height);
+    assert!(test_arrs(2, &image.dat, &[0, 1]));
+    assert!(decoder.next().is_none());
+    let _ = fs::remove_file("test_9.pbm");
+}
+
+#[test]
+fn pbm_in_memory() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&F, 10, 9, Mode::BINARY);
+        let _ = encoder.save(&J, 6, 10, Mode::ASCII);
+    }
+    let decoder = PBMDecoder::from_reader(&buffer[..]);
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(2, images.len());
+    assert!(test_arrs(90, &images[0].dat, &F));
+    assert!(test_arrs(60, &images[1].dat, &J));
+}
+
+#[test]
+fn pbm_rows() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&F, 10, 9, Mode::BINARY);
+        let _ = encoder.save(&J, 6, 10, Mode::ASCII);
+    }
+    let mut decoder = PBMDecoder::from_reader(&buffer[..]);
+    {
+        let mut rows = decoder.rows().unwrap();
+        assert_eq!(10, rows.row_len());
+        let mut row = [0u8;10];
+        let mut count = 0;
+        while rows.read_row(&mut row).unwrap() {
+            assert!(test_arrs(10, &row, &F[count * 10..]));
+            count += 1;
+        }
+        assert_eq!(9, count);
+    }
+    let dat:Vec<u8> = decoder.rows().unwrap().flat_map(|r| r.unwrap()).collect();
+    assert_eq!(60, dat.len());
+    assert!(test_arrs(60, &dat, &J));
+    assert!(decoder.next().is_none());
+}
+
+#[test]
+fn pbm_row_writer() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        {
+            let mut writer = encoder.rows(10, 9, Mode::BINARY).unwrap();
+            for row in F.chunks(10) {
+                writer.write_row(row).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        // wider than a single line of an ascii file.
+        let mut writer = encoder.rows(100, 2, Mode::ASCII).unwrap();
+        writer.write_row(&[1;100]).unwrap();
+        writer.write_row(&[0;100]).unwrap();
+        writer.finish().unwrap();
+    }
+    let mut decoder = PBMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert!(test_arrs(90, &image.dat, &F));
+    let image = decoder.load().unwrap();
+    assert_eq!(200, image.dat.len());
+    assert!(test_arrs(100, &image.dat, &[1;100]));
+    assert!(test_arrs(100, &image.dat[100..], &[0;100]));
+}
+
+#[test]
+fn pbm_comments() {
+    let comments = [Comment{text: "scanned".to_string(), position: 0},
+                    Comment{text: "".to_string(), position: 5}];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        encoder.set_comments(&comments);
+        let mut writer = encoder.rows(10, 9, Mode::ASCII).unwrap();
+        for row in F.chunks(10) {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    assert!(buffer.starts_with(b"P1\n# scanned\n10\n#\n9\n"));
+    let mut decoder = PBMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(2, image.comments.len());
+    assert_eq!(comments[0], image.comments[0]);
+    // comments can't come after the last value so it moves before it.
+    assert_eq!(1, image.comments[1].position);
+    assert!(test_arrs(90, &image.dat, &F));
+}
+
+#[test]
+fn pbm_save_wrong_len() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PBMEncoder::from_writer(&mut buffer);
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        match encoder.save(&[1,0,1], 2, 2, mode) {
+            Err(Error::DimensionMismatch{expected: 4, found: 3}) => {},
+            _ => assert!(false),
+        }
+        match encoder.save(&[1,0,1,0,1], 2, 2, mode) {
+            Err(Error::DimensionMismatch{expected: 4, found: 5}) => {},
+            _ => assert!(false),
+        }
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn pbm_save_stride() {
+    let dat:[u8;15] = [1,0,0,0,1, 9,9,9,9,9,
+                       0,1,1,1,0];
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        let mut buffer:Vec<u8> = vec![];
+        {
+            let mut encoder = PBMEncoder::from_writer(&mut buffer);
+            encoder.save_stride(&dat, 5, 2, 10, mode).unwrap();
+        }
+        let image = PBMDecoder::from_reader(&buffer[..]).load().unwrap();
+        assert!(test_arrs(10, &image.dat, &[1,0,0,0,1, 0,1,1,1,0]));
+    }
+    let mut encoder = PBMEncoder::from_writer(vec![]);
+    match encoder.save_stride(&dat, 5, 2, 4, Mode::BINARY) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+    match encoder.save_stride(&dat, 5, 3, 10, Mode::BINARY) {
+        Err(Error::DimensionMismatch{expected: 25, found: 15}) => {},
+        _ => assert!(false),
+    }
+}
@@ -0,0 +1,133 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pfm::{ByteOrder,PFMEncoder,PFMDecoder};
+use netbpm::Error;
+use std::fs;
+
+#[test]
+fn pfm_gray_little_endian() {
+    let data:&[u8] = b"Pf\n2 2\n-1.0\n\x00\x00\x80\x3f\x00\x00\x00\x40\x00\x00\x00\x00\x00\x00\x00\xbf";
+    let mut decoder = PFMDecoder::from_reader(data);
+    let image = decoder.load().unwrap();
+    assert_eq!(2, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(1, image.channels);
+    assert_eq!(ByteOrder::LITTLE, image.byte_order);
+    assert_eq!(1.0, image.scale);
+    // The bottom row is stored first.
+    assert_eq!(vec![0.0, -0.5, 1.0, 2.0], image.dat);
+}
+
+#[test]
+fn pfm_color_big_endian() {
+    let data:&[u8] = b"PF\n1 1\n4.5\n\x3f\x80\x00\x00\x3f\x00\x00\x00\x3e\x80\x00\x00";
+    let mut decoder = PFMDecoder::from_reader(data);
+    let image = decoder.load().unwrap();
+    assert_eq!(3, image.channels);
+    assert_eq!(ByteOrder::BIG, image.byte_order);
+    assert_eq!(4.5, image.scale);
+    assert_eq!(vec![1.0, 0.5, 0.25], image.dat);
+}
+
+#[test]
+fn pfm_encoder_layout() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PFMEncoder::from_writer(&mut buffer);
+        encoder.save(&[1.0, 2.0], 1, 2, 1, ByteOrder::LITTLE).unwrap();
+    }
+    assert_eq!(b"Pf\n1 2\n-1.0\n\x00\x00\x00\x40\x00\x00\x80\x3f".to_vec(), buffer);
+}
+
+#[test]
+fn pfm_round_trip() {
+    let dat:Vec<f32> = vec![0.0, 1.5, -3.25, 1e10, 0.1, 7.0,
+                            f32::MAX, f32::MIN_POSITIVE, -0.0, 2.5, 3.5, 4.5];
+    for &byte_order in &[ByteOrder::BIG, ByteOrder::LITTLE] {
+        let mut encoder = PFMEncoder::new("test_0.pfm").unwrap();
+        encoder.save(&dat, 2, 2, 3, byte_order).unwrap();
+        let mut decoder = PFMDecoder::new("test_0.pfm").unwrap();
+        let image = decoder.load().unwrap();
+        assert_eq!(byte_order, image.byte_order);
+        assert_eq!(dat, image.dat);
+        let _ = fs::remove_file("test_0.pfm");
+    }
+}
+
+#[test]
+fn pfm_scale_round_trip() {
+    for &byte_order in &[ByteOrder::BIG, ByteOrder::LITTLE] {
+        let mut buffer:Vec<u8> = vec![];
+        {
+            let mut encoder = PFMEncoder::from_writer(&mut buffer);
+            encoder.save_scale(&[0.25, 4.0], 2, 1, 1, byte_order, 2.5).unwrap();
+        }
+        let image = PFMDecoder::from_reader(&buffer[..]).load().unwrap();
+        assert_eq!(2.5, image.scale);
+        assert_eq!(byte_order, image.byte_order);
+        assert_eq!(vec![0.25, 4.0], image.dat);
+    }
+}
+
+#[test]
+fn pfm_multiple_images() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PFMEncoder::from_writer(&mut buffer);
+        encoder.save(&[1.0], 1, 1, 1, ByteOrder::BIG).unwrap();
+        encoder.save(&[2.0, 3.0, 4.0], 1, 1, 3, ByteOrder::LITTLE).unwrap();
+    }
+    let images:Vec<_> = PFMDecoder::from_reader(&buffer[..]).map(|i| i.unwrap()).collect();
+    assert_eq!(2, images.len());
+    assert_eq!(vec![1.0], images[0].dat);
+    assert_eq!(vec![2.0, 3.0, 4.0], images[1].dat);
+}
+
+#[test]
+fn pfm_encoder_errors() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PFMEncoder::from_writer(&mut buffer);
+    match encoder.save(&[1.0, 2.0], 1, 1, 2, ByteOrder::BIG) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+    match encoder.save(&[1.0, 2.0], 1, 1, 3, ByteOrder::BIG) {
+        Err(Error::DimensionMismatch{expected: 12, found: 8}) => {},
+        _ => assert!(false),
+    }
+    for &scale in &[0.0, -1.0, f32::NAN, f32::INFINITY] {
+        match encoder.save_scale(&[1.0], 1, 1, 1, ByteOrder::BIG, scale) {
+            Err(Error::InvalidParameter(_)) => {},
+            _ => assert!(false),
+        }
+    }
+}
+
+#[test]
+fn pfm_decoder_errors() {
+    match PFMDecoder::from_reader(&b"P5\n1 1\n255\n\x00"[..]).load() {
+        Err(Error::BadMagicNumber{expected: Some("pfm")}) => {},
+        _ => assert!(false),
+    }
+    match PFMDecoder::from_reader(&b"Pf\n1 1\n0.0\n\x00\x00\x00\x00"[..]).load() {
+        Err(Error::InvalidHeader(_)) => {},
+        _ => assert!(false),
+    }
+    match PFMDecoder::from_reader(&b"Pf\n1 x\n1.0\n\x00\x00\x00\x00"[..]).load() {
+        Err(Error::InvalidHeader(_)) => {},
+        _ => assert!(false),
+    }
+    match PFMDecoder::from_reader(&b"Pf\n2 1\n1.0\n\x00\x00\x00\x00"[..]).load() {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+    match PFMDecoder::from_reader(&b"Pf\n2 1"[..]).load() {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+    match PFMDecoder::from_reader(&b""[..]).load() {
+        Err(Error::NoMoreImages) => {},
+        _ => assert!(false),
+    }
+}
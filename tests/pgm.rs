@@ -1,8 +1,12 @@
+#![allow(clippy::assertions_on_constants)]
+
 extern crate netbpm;
 use netbpm::pgm::{PGMEncoder,PGMDecoder};
-use netbpm::{Mode,BitDepth};
+use netbpm::{Mode,BitDepth,Comment,Error};
+use std::error::Error as StdError;
 use std::fs;
-use std::error::Error;
+use std::io;
+use std::io::prelude::*;
 
 // 6 10
 const J:[u8;60] = [255,255,255,255,0,255,
@@ -96,6 +100,22 @@ const LONG_GRAD_DOUBLE:[u8;1200] = [255,255,255,255,255,255,255,255,255,255,   2
                             255,255,255,255,255,255,255,255,255,255,   200,200,200,200,200,200,200,200,200,200,   150,150,150,150,150,150,150,150,150,150,   100,100,100,100,100,100,100,100,100,100,   50,50,50,50,50,50,50,50,50,50,   0,0,0,0,0,0,0,0,0,0];
 
 
+// Hands out data one byte at a time, like a slow pipe.
+struct SlowReader<'a> {
+    dat: &'a [u8],
+}
+
+impl<'a> Read for SlowReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.dat.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.dat[0];
+        self.dat = &self.dat[1..];
+        Ok(1)
+    }
+}
+
 fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
     let mut ret = true;
     for i in 0..size {
@@ -107,13 +127,13 @@ fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
 
 #[test]
 fn pgm_j_single_ascii() {
-    let mut encoder = PGMEncoder::new("test_0a.pgm");
+    let mut encoder = PGMEncoder::new("test_0a.pgm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_0a.pgm");
+    let mut decoder = PGMDecoder::new("test_0a.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -124,13 +144,13 @@ fn pgm_j_single_ascii() {
 
 #[test]
 fn pgm_j_single_binary() {
-    let mut encoder = PGMEncoder::new("test_0b.pgm");
+    let mut encoder = PGMEncoder::new("test_0b.pgm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_0b.pgm");
+    let mut decoder = PGMDecoder::new("test_0b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -141,13 +161,13 @@ fn pgm_j_single_binary() {
 
 #[test]
 fn pgm_j_double_ascii() {
-    let mut encoder = PGMEncoder::new("test_1a.pgm");
+    let mut encoder = PGMEncoder::new("test_1a.pgm").unwrap();
     let enc_result = encoder.save(&J_DOUBLE, 6, 10, Mode::ASCII, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_1a.pgm");
+    let mut decoder = PGMDecoder::new("test_1a.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -158,13 +178,13 @@ fn pgm_j_double_ascii() {
 
 #[test]
 fn pgm_j_double_binary() {
-    let mut encoder = PGMEncoder::new("test_1b.pgm");
+    let mut encoder = PGMEncoder::new("test_1b.pgm").unwrap();
     let enc_result = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_1b.pgm");
+    let mut decoder = PGMDecoder::new("test_1b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -175,13 +195,13 @@ fn pgm_j_double_binary() {
 
 #[test]
 fn pgm_grad_single_ascii() {
-    let mut encoder = PGMEncoder::new("test_2a.pgm");
+    let mut encoder = PGMEncoder::new("test_2a.pgm").unwrap();
     let enc_result = encoder.save(&GRAD, 12, 10, Mode::ASCII, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_2a.pgm");
+    let mut decoder = PGMDecoder::new("test_2a.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(12, image.width);
     assert_eq!(10, image.height);
@@ -192,13 +212,13 @@ fn pgm_grad_single_ascii() {
 
 #[test]
 fn pgm_grad_single_binary() {
-    let mut encoder = PGMEncoder::new("test_2b.pgm");
+    let mut encoder = PGMEncoder::new("test_2b.pgm").unwrap();
     let enc_result = encoder.save(&GRAD, 12, 10, Mode::BINARY, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_2b.pgm");
+    let mut decoder = PGMDecoder::new("test_2b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(12, image.width);
     assert_eq!(10, image.height);
@@ -209,13 +229,13 @@ fn pgm_grad_single_binary() {
 
 #[test]
 fn pgm_grad_double_ascii() {
-    let mut encoder = PGMEncoder::new("test_3a.pgm");
+    let mut encoder = PGMEncoder::new("test_3a.pgm").unwrap();
     let enc_result = encoder.save(&GRAD_DOUBLE, 12, 10, Mode::ASCII, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_3a.pgm");
+    let mut decoder = PGMDecoder::new("test_3a.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(12, image.width);
     assert_eq!(10, image.height);
@@ -226,13 +246,13 @@ fn pgm_grad_double_ascii() {
 
 #[test]
 fn pgm_grad_double_binary() {
-    let mut encoder = PGMEncoder::new("test_3b.pgm");
+    let mut encoder = PGMEncoder::new("test_3b.pgm").unwrap();
     let enc_result = encoder.save(&GRAD_DOUBLE, 12, 10, Mode::BINARY, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_3b.pgm");
+    let mut decoder = PGMDecoder::new("test_3b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(12, image.width);
     assert_eq!(10, image.height);
@@ -242,25 +262,34 @@ fn pgm_grad_double_binary() {
 }
 
 #[test]
-fn pgm_long_grad_single_ascii_error() {
-    let mut encoder = PGMEncoder::new("test_4a.pgm");
+fn pgm_long_grad_single_ascii() {
+    let mut encoder = PGMEncoder::new("test_4a.pgm").unwrap();
     let enc_result = encoder.save(&LONG_GRAD, 30, 20, Mode::ASCII, BitDepth::EIGHT);
     match enc_result {
-        Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Width can not be greater than 70 characters for ascii pgm files."),
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
     }
+    let mut contents = String::new();
+    let _ = fs::File::open("test_4a.pgm").unwrap().read_to_string(&mut contents);
+    assert!(contents.lines().all(|l| l.len() <= 70));
+    let mut decoder = PGMDecoder::new("test_4a.pgm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(30, image.width);
+    assert_eq!(20, image.height);
+    assert_eq!(BitDepth::EIGHT, image.depth);
+    assert!(test_arrs(600, &image.dat, &LONG_GRAD));
     let _ = fs::remove_file("test_4a.pgm");
 }
 
 #[test]
 fn pgm_long_grad_single_binary() {
-    let mut encoder = PGMEncoder::new("test_4b.pgm");
+    let mut encoder = PGMEncoder::new("test_4b.pgm").unwrap();
     let enc_result = encoder.save(&LONG_GRAD, 30, 20, Mode::BINARY, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_4b.pgm");
+    let mut decoder = PGMDecoder::new("test_4b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(30, image.width);
     assert_eq!(20, image.height);
@@ -271,13 +300,13 @@ fn pgm_long_grad_single_binary() {
 
 #[test]
 fn pgm_long_grad_double_binary() {
-    let mut encoder = PGMEncoder::new("test_5b.pgm");
+    let mut encoder = PGMEncoder::new("test_5b.pgm").unwrap();
     let enc_result = encoder.save(&LONG_GRAD_DOUBLE, 30, 20, Mode::BINARY, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PGMDecoder::new("test_5b.pgm");
+    let mut decoder = PGMDecoder::new("test_5b.pgm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(30, image.width);
     assert_eq!(20, image.height);
@@ -285,3 +314,528 @@ fn pgm_long_grad_double_binary() {
     assert!(test_arrs(1200, &image.dat, &LONG_GRAD_DOUBLE));
     let _ = fs::remove_file("test_5b.pgm");
 }
+
+// 4 2, max value 4095
+const TWELVE_BIT:[u8;16] = [0,0, 4,0, 8,0, 15,255,
+                            15,255, 8,0, 4,0, 0,0];
+
+#[test]
+fn pgm_twelve_bit_ascii() {
+    let mut encoder = PGMEncoder::new("test_6a.pgm").unwrap();
+    let enc_result = encoder.save_max_val(&TWELVE_BIT, 4, 2, Mode::ASCII, 4095);
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PGMDecoder::new("test_6a.pgm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(4, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert_eq!(4095, image.max_val);
+    assert!(test_arrs(16, &image.dat, &TWELVE_BIT));
+    let _ = fs::remove_file("test_6a.pgm");
+}
+
+#[test]
+fn pgm_twelve_bit_binary() {
+    let mut encoder = PGMEncoder::new("test_6b.pgm").unwrap();
+    let enc_result = encoder.save_max_val(&TWELVE_BIT, 4, 2, Mode::BINARY, 4095);
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PGMDecoder::new("test_6b.pgm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(4, image.width);
+    assert_eq!(2, image.height);
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert_eq!(4095, image.max_val);
+    assert!(test_arrs(16, &image.dat, &TWELVE_BIT));
+    let _ = fs::remove_file("test_6b.pgm");
+}
+
+#[test]
+fn pgm_twelve_bit_rescaled() {
+    let mut encoder = PGMEncoder::new("test_6c.pgm").unwrap();
+    let enc_result = encoder.save_max_val(&TWELVE_BIT, 4, 2, Mode::BINARY, 4095);
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PGMDecoder::new("test_6c.pgm").unwrap();
+    let image = decoder.load_rescaled().unwrap();
+    assert_eq!(65535, image.max_val);
+    // 1024 -> 16388, 2048 -> 32776, 4095 -> 65535
+    let expected:[u8;16] = [0,0, 64,4, 128,8, 255,255,
+                            255,255, 128,8, 64,4, 0,0];
+    assert!(test_arrs(16, &image.dat, &expected));
+    let _ = fs::remove_file("test_6c.pgm");
+}
+
+#[test]
+fn pgm_four_bit_rescaled() {
+    let dat:[u8;4] = [0, 5, 10, 15];
+    let mut encoder = PGMEncoder::new("test_7.pgm").unwrap();
+    let enc_result = encoder.save_max_val(&dat, 4, 1, Mode::ASCII, 15);
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PGMDecoder::new("test_7.pgm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(BitDepth::EIGHT, image.depth);
+    assert_eq!(15, image.max_val);
+    assert!(test_arrs(4, &image.dat, &dat));
+    let mut decoder = PGMDecoder::new("test_7.pgm").unwrap();
+    let image = decoder.load_rescaled().unwrap();
+    assert_eq!(255, image.max_val);
+    assert!(test_arrs(4, &image.dat, &[0, 85, 170, 255]));
+    let _ = fs::remove_file("test_7.pgm");
+}
+
+#[test]
+fn pgm_zero_max_val_error() {
+    let mut encoder = PGMEncoder::new("test_8.pgm").unwrap();
+    let enc_result = encoder.save_max_val(&J, 6, 10, Mode::BINARY, 0);
+    match enc_result {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::UnsupportedMaxVal(max_val) => assert_eq!(max_val, 0),
+            _ => assert!(false),
+        },
+    }
+    let _ = fs::remove_file("test_8.pgm");
+}
+
+#[test]
+fn pgm_multiple_images() {
+    let mut encoder = PGMEncoder::new("test_9.pgm").unwrap();
+    let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+    let _ = encoder.save(&GRAD, 12, 10, Mode::ASCII, BitDepth::EIGHT);
+    let _ = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
+    let mut decoder = PGMDecoder::new("test_9.pgm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(6, image.width);
+    assert_eq!(60, image.dat.len());
+    assert!(test_arrs(60, &image.dat, &J));
+    let image = decoder.load().unwrap();
+    assert_eq!(12, image.width);
+    assert_eq!(120, image.dat.len());
+    assert!(test_arrs(120, &image.dat, &GRAD));
+    let image = decoder.load().unwrap();
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert_eq!(120, image.dat.len());
+    assert!(test_arrs(120, &image.dat, &J_DOUBLE));
+    match decoder.load() {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(),"No more images in file."),
+    }
+    let _ = fs::remove_file("test_9.pgm");
+}
+
+#[test]
+fn pgm_append_iterate() {
+    let mut encoder = PGMEncoder::new("test_10.pgm").unwrap();
+    let _ = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
+    for _ in 0..3 {
+        let mut encoder = PGMEncoder::append("test_10.pgm").unwrap();
+        let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+    }
+    let decoder = PGMDecoder::new("test_10.pgm").unwrap();
+    let mut count = 0;
+    for image in decoder {
+        let image = image.unwrap();
+        assert!(test_arrs(60, &image.dat, &J));
+        count += 1;
+    }
+    assert_eq!(4, count);
+    let _ = fs::remove_file("test_10.pgm");
+}
+
+#[test]
+fn pgm_in_memory() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
+        let _ = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
+    }
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(6, image.width);
+    assert_eq!(10, image.height);
+    assert!(test_arrs(60, &image.dat, &J));
+    let image = decoder.load().unwrap();
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert!(test_arrs(120, &image.dat, &J_DOUBLE));
+    assert!(decoder.next().is_none());
+}
+
+#[test]
+fn pgm_into_inner() {
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    let _ = encoder.save(&[0, 255], 2, 1, Mode::BINARY, BitDepth::EIGHT);
+    let buffer = encoder.into_inner();
+    assert_eq!(b"P5\n2 1\n255\n\x00\xff".to_vec(), buffer);
+}
+
+#[test]
+fn pgm_slow_reader() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&GRAD, 12, 10, Mode::ASCII, BitDepth::EIGHT);
+        let _ = encoder.save(&GRAD_DOUBLE, 12, 10, Mode::BINARY, BitDepth::SIXTEEN);
+    }
+    let decoder = PGMDecoder::from_reader(SlowReader{dat: &buffer});
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(2, images.len());
+    assert!(test_arrs(120, &images[0].dat, &GRAD));
+    assert!(test_arrs(240, &images[1].dat, &GRAD_DOUBLE));
+}
+
+#[test]
+fn pgm_reads_only_first_image() {
+    // The decoder must not wait for data past the end of the image it's loading.
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+    }
+    let mut reader = SlowReader{dat: &buffer};
+    let image = {
+        let mut decoder = PGMDecoder::from_reader(&mut reader);
+        decoder.load().unwrap()
+    };
+    assert!(test_arrs(60, &image.dat, &J));
+    assert!(reader.dat.is_empty());
+}
+
+#[test]
+fn pgm_missing_file_error() {
+    match PGMDecoder::new("does_not_exist.pgm") {
+        Ok(_) => assert!(false),
+        Err(e) => {
+            assert!(e.source().is_some());
+            match e {
+                Error::Io(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+                _ => assert!(false),
+            }
+        },
+    }
+}
+
+#[test]
+fn pgm_wrong_filetype_error() {
+    let mut decoder = PGMDecoder::from_reader(&b"P3\n1 1\n255\n0 0 0\n"[..]);
+    match decoder.load() {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::BadMagicNumber{expected} => assert_eq!(expected, Some("pgm")),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn pgm_no_more_images_error() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n1 1\n255\n7\n"[..]);
+    assert!(decoder.load().is_ok());
+    match decoder.load() {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::NoMoreImages => {},
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn pgm_error_into_io_error() {
+    fn load() -> io::Result<netbpm::Image> {
+        let mut decoder = PGMDecoder::from_reader(&b"P2\n1 1\n0\n7\n"[..]);
+        Ok(decoder.load()?)
+    }
+    match load() {
+        Ok(_) => assert!(false),
+        Err(e) => {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(e.to_string(), "Unsupported max value: 0");
+        },
+    }
+}
+
+#[test]
+fn pgm_rows() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&GRAD, 12, 10, Mode::ASCII, BitDepth::EIGHT);
+        let _ = encoder.save(&GRAD_DOUBLE, 12, 10, Mode::BINARY, BitDepth::SIXTEEN);
+        let _ = encoder.save(&J_DOUBLE, 6, 10, Mode::ASCII, BitDepth::SIXTEEN);
+    }
+    let mut decoder = PGMDecoder::from_reader(SlowReader{dat: &buffer});
+    let dat:Vec<u8> = decoder.rows().unwrap().flat_map(|r| r.unwrap()).collect();
+    assert_eq!(120, dat.len());
+    assert!(test_arrs(120, &dat, &GRAD));
+    {
+        let mut rows = decoder.rows().unwrap();
+        assert_eq!(12, rows.width());
+        assert_eq!(10, rows.height());
+        assert_eq!(BitDepth::SIXTEEN, rows.depth());
+        assert_eq!(Mode::BINARY, rows.mode());
+        assert_eq!(24, rows.row_len());
+        let mut row = [0u8;24];
+        let mut count = 0;
+        while rows.read_row(&mut row).unwrap() {
+            assert!(test_arrs(24, &row, &GRAD_DOUBLE[count * 24..]));
+            count += 1;
+        }
+        assert_eq!(10, count);
+    }
+    // the decoder carries on after the last row.
+    let image = decoder.load().unwrap();
+    assert!(test_arrs(120, &image.dat, &J_DOUBLE));
+    assert!(decoder.next().is_none());
+}
+
+#[test]
+fn pgm_rows_truncated_error() {
+    let mut decoder = PGMDecoder::from_reader(&b"P5\n2 2\n255\n\x00\xff\x00"[..]);
+    let mut rows = decoder.rows().unwrap();
+    assert_eq!(vec![0, 255], rows.next().unwrap().unwrap());
+    match rows.next() {
+        Some(Err(Error::Truncated)) => assert!(true),
+        _ => assert!(false),
+    }
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn pgm_rows_buffer_length_error() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n255\n0 1 2\n"[..]);
+    let mut rows = decoder.rows().unwrap();
+    let mut row = [0u8;2];
+    match rows.read_row(&mut row) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::DimensionMismatch{expected, found} => {
+                assert_eq!(3, expected);
+                assert_eq!(2, found);
+            },
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn pgm_row_writer() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        {
+            let mut writer = encoder.rows(30, 20, Mode::ASCII, BitDepth::SIXTEEN).unwrap();
+            assert_eq!(60, writer.row_len());
+            for row in LONG_GRAD_DOUBLE.chunks(60) {
+                writer.write_row(row).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        let mut writer = encoder.rows(12, 10, Mode::BINARY, BitDepth::EIGHT).unwrap();
+        for row in GRAD.chunks(12) {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    // long rows are split over several lines.
+    let text = String::from_utf8_lossy(&buffer).into_owned();
+    let ascii = text.split("P5").next().unwrap();
+    assert!(ascii.lines().count() > 21);
+    assert!(ascii.lines().all(|l| l.len() <= 70));
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(30, image.width);
+    assert_eq!(20, image.height);
+    assert!(test_arrs(1200, &image.dat, &LONG_GRAD_DOUBLE));
+    let image = decoder.load().unwrap();
+    assert!(test_arrs(120, &image.dat, &GRAD));
+}
+
+#[test]
+fn pgm_row_writer_too_few_rows_error() {
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    let mut writer = encoder.rows(2, 2, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    writer.write_row(&[0, 255]).unwrap();
+    match writer.finish() {
+        Ok(()) => assert!(false),
+        Err(e) => match e {
+            Error::RowCount{expected, found} => {
+                assert_eq!(2, expected);
+                assert_eq!(1, found);
+            },
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn pgm_row_writer_too_many_rows_error() {
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    let mut writer = encoder.rows(2, 1, Mode::ASCII, BitDepth::EIGHT).unwrap();
+    writer.write_row(&[0, 255]).unwrap();
+    match writer.write_row(&[255, 0]) {
+        Ok(()) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(), "Expected 1 rows but found 2."),
+    }
+    assert!(writer.finish().is_ok());
+}
+
+#[test]
+fn pgm_comments() {
+    let comments = [Comment{text: "camera 2".to_string(), position: 0},
+                    Comment{text: "exposure 1/60".to_string(), position: 1},
+                    Comment{text: "pipeline v4".to_string(), position: 2}];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        encoder.set_comments(&comments);
+        let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+        encoder.set_comments(&[]);
+        let _ = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
+    }
+    assert!(buffer.starts_with(b"P5\n# camera 2\n6\n# exposure 1/60\n10\n# pipeline v4\n255\n"));
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(comments.to_vec(), image.comments);
+    assert!(test_arrs(60, &image.dat, &J));
+    let image = decoder.load().unwrap();
+    assert!(image.comments.is_empty());
+    assert!(test_arrs(60, &image.dat, &J));
+}
+
+#[test]
+fn pgm_comment_line_break_error() {
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    encoder.set_comments(&[Comment{text: "two\nlines".to_string(), position: 0}]);
+    match encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT) {
+        Ok(()) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(), "Comments can not contain a line break."),
+    }
+    assert!(encoder.into_inner().is_empty());
+}
+
+#[test]
+fn pgm_u16() {
+    let dat:[u16;4] = [0, 1024, 4095, 2048];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save_u16(&dat, 2, 2, Mode::BINARY, 4095);
+        let _ = encoder.save_u16(&dat, 2, 2, Mode::ASCII, 4095);
+        let _ = encoder.save_u16(&[0, 15, 7, 3], 2, 2, Mode::BINARY, 15);
+    }
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert!(test_arrs(8, &image.dat, &[0,0, 4,0, 15,255, 8,0]));
+    let image = decoder.load_u16().unwrap();
+    assert_eq!(4095, image.max_val);
+    assert_eq!(dat.to_vec(), image.dat);
+    // eight-bit samples are widened but not scaled.
+    let image = decoder.load_u16().unwrap();
+    assert_eq!(15, image.max_val);
+    assert_eq!(vec![0, 15, 7, 3], image.dat);
+}
+
+#[test]
+fn pgm_u16_over_max_val_error() {
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    match encoder.save_u16(&[0, 256], 2, 1, Mode::BINARY, 255) {
+        Ok(()) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(), "Samples can not be greater than the max value."),
+    }
+}
+
+#[test]
+fn pgm_save_wrong_len() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PGMEncoder::from_writer(&mut buffer);
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        match encoder.save(&J[..59], 6, 10, mode, BitDepth::EIGHT) {
+            Err(Error::DimensionMismatch{expected: 60, found: 59}) => {},
+            _ => assert!(false),
+        }
+        match encoder.save(&J, 6, 10, mode, BitDepth::SIXTEEN) {
+            Err(Error::DimensionMismatch{expected: 120, found: 60}) => {},
+            _ => assert!(false),
+        }
+        match encoder.save_u16(&[1,2,3], 2, 2, mode, 1000) {
+            Err(Error::DimensionMismatch{expected: 8, found: 6}) => {},
+            _ => assert!(false),
+        }
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn pgm_save_sample_over_max_val() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PGMEncoder::from_writer(&mut buffer);
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        match encoder.save_max_val(&[0, 100, 101, 7], 2, 2, mode, 100) {
+            Err(Error::SampleOutOfRange{value: 101, max_val: 100}) => {},
+            _ => assert!(false),
+        }
+        match encoder.save_stride(&[0, 9, 9, 9, 16, 0], 1, 2, 4, mode, 4095) {
+            Err(Error::SampleOutOfRange{value: 4096, max_val: 4095}) => {},
+            _ => assert!(false),
+        }
+    }
+    assert!(buffer.is_empty());
+
+    let mut encoder = PGMEncoder::from_writer(Vec::new());
+    let mut writer = encoder.rows_max_val(2, 1, Mode::BINARY, 10).unwrap();
+    match writer.write_row(&[10, 11]) {
+        Err(Error::SampleOutOfRange{value: 11, max_val: 10}) => {},
+        _ => assert!(false),
+    }
+    writer.write_row(&[10, 0]).unwrap();
+    writer.finish().unwrap();
+}
+
+#[test]
+fn pgm_save_stride() {
+    // A 2x2 view into a 4x3 image.
+    let dat:[u8;12] = [1,2,3,4,
+                       5,6,7,8,
+                       9,10,11,12];
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        let mut buffer:Vec<u8> = vec![];
+        {
+            let mut encoder = PGMEncoder::from_writer(&mut buffer);
+            encoder.save_stride(&dat[5..], 2, 2, 4, mode, 255).unwrap();
+        }
+        let image = PGMDecoder::from_reader(&buffer[..]).load().unwrap();
+        assert!(test_arrs(4, &image.dat, &[6,7, 10,11]));
+    }
+    let mut encoder = PGMEncoder::from_writer(vec![]);
+    match encoder.save_stride(&dat, 4, 3, 3, Mode::BINARY, 255) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+    match encoder.save_stride(&dat[5..], 2, 3, 4, Mode::BINARY, 255) {
+        Err(Error::DimensionMismatch{expected: 10, found: 7}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn pgm_multi_space_header() {
+    // Headers as written by some other programs, with runs of whitespace and CRLF line endings.
+    let dat:&[u8] = b"P5\r\n# Created by GIMP\r\n3  2\r\n255\n\x01\x02\x03\x0a\x0d\x20";
+    let image = PGMDecoder::from_reader(dat).load().unwrap();
+    assert_eq!(image.width, 3);
+    assert_eq!(image.height, 2);
+    assert!(test_arrs(6, &image.dat, &[1,2,3,10,13,32]));
+    assert_eq!(image.comments[0].text, "Created by GIMP");
+}
@@ -0,0 +1,167 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pam::{PAMEncoder,PAMDecoder};
+use netbpm::pbm::PBMDecoder;
+use netbpm::pgm::{PGMEncoder,PGMDecoder};
+use netbpm::pixel::{Bit,ImageBuffer,Luma,LumaA,Rgb,Rgba};
+use netbpm::ppm::PPMDecoder;
+use netbpm::{BitDepth,Comment,Error,Mode};
+
+#[test]
+fn pixel_rgb() {
+    let mut decoder = PPMDecoder::from_reader(&b"P3\n2 2\n255\n255 0 0  0 255 0\n0 0 255  9 9 9\n"[..]);
+    let mut image = ImageBuffer::<Rgb<u8>>::from_image(&decoder.load().unwrap()).unwrap();
+    assert_eq!(2, image.width());
+    assert_eq!(2, image.height());
+    assert_eq!(Rgb([0, 255, 0]), image.get_pixel(1, 0));
+    assert_eq!(Rgb([0, 0, 255]), image.get_pixel(0, 1));
+    image.put_pixel(1, 1, Rgb([1, 2, 3]));
+    assert_eq!(&[Rgb([0, 0, 255]), Rgb([1, 2, 3])], image.row(1));
+    assert_eq!(2, image.rows().count());
+    assert_eq!(vec![255,0,0, 0,255,0, 0,0,255, 1,2,3], image.to_bytes());
+}
+
+#[test]
+fn pixel_luma_sixteen() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save_u16(&[0, 1000, 60000], 3, 1, Mode::BINARY, 65535);
+    }
+    let mut decoder = PGMDecoder::from_reader(&buffer[..]);
+    let mut image = ImageBuffer::<Luma<u16>>::from_image(&decoder.load().unwrap()).unwrap();
+    assert_eq!(Luma([60000]), image.get_pixel(2, 0));
+    for pixel in image.pixels_mut() {
+        pixel.0[0] /= 2;
+    }
+    let values:Vec<u16> = image.pixels().map(|p| p.0[0]).collect();
+    assert_eq!(vec![0, 500, 30000], values);
+    let coords:Vec<(u32, u32)> = image.enumerate_pixels().map(|(x, y, _)| (x, y)).collect();
+    assert_eq!(vec![(0, 0), (1, 0), (2, 0)], coords);
+    assert_eq!(vec![0,0, 1,244, 117,48], image.to_bytes());
+}
+
+#[test]
+fn pixel_keeps_max_val_and_comments() {
+    let comments = [Comment{text: "12-bit scan".to_string(), position: 0}];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        encoder.set_comments(&comments);
+        let _ = encoder.save_u16(&[0, 1000, 4095], 3, 1, Mode::BINARY, 4095);
+    }
+    let image = PGMDecoder::from_reader(&buffer[..]).load().unwrap();
+    let mut pixels = ImageBuffer::<Luma<u16>>::from_image(&image).unwrap();
+    assert_eq!(4095, pixels.max_val());
+    assert_eq!(&comments, pixels.comments());
+    pixels.put_pixel(0, 0, Luma([2048]));
+
+    let image = pixels.to_image();
+    let mut saved:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut saved);
+        encoder.set_comments(&image.comments);
+        encoder.save_max_val(&image.dat, image.width, image.height, Mode::BINARY, image.max_val).unwrap();
+    }
+    let image = PGMDecoder::from_reader(&saved[..]).load_u16().unwrap();
+    assert_eq!(4095, image.max_val);
+    assert_eq!(comments.to_vec(), image.comments);
+    assert_eq!(vec![2048, 1000, 4095], image.dat);
+}
+
+#[test]
+fn pixel_set_max_val() {
+    let mut image = ImageBuffer::<Luma<u16>>::new(1, 1);
+    assert_eq!(65535, image.max_val());
+    image.set_max_val(1023).unwrap();
+    assert_eq!(1023, image.to_image().max_val);
+    match image.set_max_val(255) {
+        Err(Error::UnsupportedMaxVal(255)) => {},
+        _ => assert!(false),
+    }
+    let mut bits = ImageBuffer::<Bit>::new(1, 1);
+    assert_eq!(1, bits.max_val());
+    bits.set_max_val(1).unwrap();
+    match bits.set_max_val(2) {
+        Err(Error::UnsupportedMaxVal(2)) => {},
+        _ => assert!(false),
+    }
+    assert_eq!(1, bits.max_val());
+    assert_eq!(255, ImageBuffer::<Rgb<u8>>::new(1, 1).max_val());
+}
+
+#[test]
+fn pixel_bit() {
+    let mut decoder = PBMDecoder::from_reader(&b"P1\n3 2\n1 0 1\n0 1 0\n"[..]);
+    let image = ImageBuffer::<Bit>::from_image(&decoder.load().unwrap()).unwrap();
+    assert_eq!(&[Bit(true), Bit(false), Bit(true)], image.row(0));
+    assert_eq!(Bit(true), image.get_pixel(1, 1));
+}
+
+#[test]
+fn pixel_alpha() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PAMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&[1,2,3,4, 5,6,7,8], 2, 1, 4, BitDepth::EIGHT, "RGB_ALPHA");
+        let _ = encoder.save(&[0,255, 255,0], 2, 1, 2, BitDepth::EIGHT, "GRAYSCALE_ALPHA");
+    }
+    let mut decoder = PAMDecoder::from_reader(&buffer[..]);
+    let image = ImageBuffer::<Rgba<u8>>::from_pam_image(&decoder.load().unwrap()).unwrap();
+    assert_eq!(Rgba([5, 6, 7, 8]), image.get_pixel(1, 0));
+    let pam = decoder.load().unwrap();
+    let image = ImageBuffer::<LumaA<u8>>::from_pam_image(&pam).unwrap();
+    assert_eq!(LumaA([255, 0]), image.get_pixel(1, 0));
+    match ImageBuffer::<Rgb<u8>>::from_pam_image(&pam) {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(), "The number of channels doesn't match the pixel type."),
+    }
+}
+
+#[test]
+fn pixel_new_and_from_pixels() {
+    let mut image = ImageBuffer::<Luma<u8>>::new(3, 2);
+    assert!(image.pixels().all(|p| *p == Luma([0])));
+    image.row_mut(1)[2] = Luma([7]);
+    assert_eq!(Luma([7]), image.get_pixel(2, 1));
+    match ImageBuffer::from_pixels(2, 2, vec![Luma([0u8]); 3]) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::DimensionMismatch{expected, found} => {
+                assert_eq!(4, expected);
+                assert_eq!(3, found);
+            },
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+fn pixel_wrong_depth_error() {
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n1 1\n255\n7\n"[..]);
+    match ImageBuffer::<Luma<u16>>::from_image(&decoder.load().unwrap()) {
+        Ok(_) => assert!(false),
+        Err(e) => assert_eq!(e.to_string(), "The bit depth doesn't match the pixel type."),
+    }
+}
+
+#[test]
+fn pixel_wrong_channels_error() {
+    // A pgm image doesn't have enough samples for rgb pixels.
+    let mut decoder = PGMDecoder::from_reader(&b"P2\n3 1\n255\n7 8 9\n"[..]);
+    match ImageBuffer::<Rgb<u8>>::from_image(&decoder.load().unwrap()) {
+        Ok(_) => assert!(false),
+        Err(e) => match e {
+            Error::DimensionMismatch{..} => assert!(true),
+            _ => assert!(false),
+        },
+    }
+}
+
+#[test]
+#[should_panic]
+fn pixel_out_of_bounds() {
+    let image = ImageBuffer::<Luma<u8>>::new(3, 2);
+    image.get_pixel(3, 0);
+}
@@ -0,0 +1,74 @@
+#![allow(clippy::assertions_on_constants)]
+
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+
+fn convert(args: &[&str], input: &[u8]) -> Vec<u8> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pnmconvert")).args(args)
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn pnmconvert_plain_to_raw() {
+    assert_eq!(b"P4\n10 1\n\x80\x40".to_vec(), convert(&["--raw"], b"P1\n10 1\n1 0 0 0 0 0 0 0 0 1\n"));
+    assert_eq!(b"P5\n3 1\n255\n\x00\x7f\xff".to_vec(), convert(&["--raw"], b"P2\n3 1\n255\n0 127 255\n"));
+    assert_eq!(b"P6\n1 1\n15\n\x01\x02\x03".to_vec(), convert(&["--raw"], b"P3\n1 1\n15\n1 2 3\n"));
+}
+
+#[test]
+fn pnmconvert_raw_to_plain() {
+    assert_eq!(b"P1\n2 1\n1 0\n".to_vec(), convert(&["--plain"], b"P4\n2 1\n\x80"));
+    assert_eq!(b"P2\n2 1\n65535\n1 65535\n".to_vec(), convert(&["--plain"], b"P5\n2 1\n65535\n\x00\x01\xff\xff"));
+    assert_eq!(b"P3\n1 1\n255\n1 2 3\n".to_vec(), convert(&["--plain"], b"P6\n1 1\n255\n\x01\x02\x03"));
+}
+
+#[test]
+fn pnmconvert_ppm_to_pgm() {
+    assert_eq!(b"P2\n3 1\n255\n76 150 29\n".to_vec(), convert(&["--pgm"], b"P3\n3 1\n255\n255 0 0  0 255 0  0 0 255\n"));
+}
+
+#[test]
+fn pnmconvert_pgm_to_pbm() {
+    assert_eq!(b"P1\n4 1\n1 1 0 0\n".to_vec(), convert(&["--pbm"], b"P2\n4 1\n255\n0 127 128 255\n"));
+    assert_eq!(b"P1\n4 1\n1 1 1 0\n".to_vec(), convert(&["--pbm", "--threshold", "0.9"], b"P2\n4 1\n255\n0 127 128 255\n"));
+}
+
+#[test]
+fn pnmconvert_promote() {
+    assert_eq!(b"P2\n2 1\n255\n0 255\n".to_vec(), convert(&["--pgm"], b"P1\n2 1\n1 0\n"));
+    assert_eq!(b"P6\n2 1\n255\n\x00\x00\x00\xff\xff\xff".to_vec(), convert(&["--ppm", "--raw"], b"P1\n2 1\n1 0\n"));
+    assert_eq!(b"P3\n2 1\n15\n0 0 0 15 15 15\n".to_vec(), convert(&["--ppm"], b"P2\n2 1\n15\n0 15\n"));
+}
+
+#[test]
+fn pnmconvert_multiple_images() {
+    assert_eq!(b"P2\n1 1\n255\n0\nP5\n1 1\n255\n\x4c".to_vec(), convert(&["--pgm"], b"P1\n1 1\n1\nP6\n1 1\n255\n\xff\x00\x00"));
+}
+
+#[test]
+fn pnmconvert_files() {
+    let mut file = File::create("test_0.pnmconvert").unwrap();
+    let _ = file.write(b"P2\n# kept\n2 1\n255\n0 255\n");
+    let status = Command::new(env!("CARGO_BIN_EXE_pnmconvert"))
+        .args(["--raw", "test_0.pnmconvert", "test_1.pnmconvert"]).status().unwrap();
+    assert!(status.success());
+    let mut dat:Vec<u8> = vec![];
+    let _ = File::open("test_1.pnmconvert").unwrap().read_to_end(&mut dat);
+    assert_eq!(b"P5\n# kept\n2 1\n255\n\x00\xff".to_vec(), dat);
+    let _ = fs::remove_file("test_0.pnmconvert");
+    let _ = fs::remove_file("test_1.pnmconvert");
+}
+
+#[test]
+fn pnmconvert_errors() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pnmconvert")).arg("--bogus").output().unwrap();
+    assert_eq!(Some(2), output.status.code());
+    let output = Command::new(env!("CARGO_BIN_EXE_pnmconvert")).arg("does_not_exist.pnmconvert").output().unwrap();
+    assert_eq!(Some(1), output.status.code());
+}
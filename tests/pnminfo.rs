@@ -0,0 +1,65 @@
+#![allow(clippy::assertions_on_constants)]
+
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+
+#[test]
+fn pnminfo_text() {
+    let mut file = File::create("test_0.pnminfo").unwrap();
+    let _ = file.write(b"P2\n# camera 2\n3 2\n255\n1 2 3\n4 5 6\nP4\n10 2\n\x00\x00\x00\x00");
+    let output = Command::new(env!("CARGO_BIN_EXE_pnminfo")).arg("test_0.pnminfo").output().unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("test_0.pnminfo: 2 images\n"));
+    assert!(text.contains("image 1: pgm plain (P2), 3x2, max value 255, 1 channel\n    # camera 2\n"));
+    assert!(text.contains("image 2: pbm raw (P4), 10x2, max value 1, 1 channel, raster 4 bytes\n"));
+    let _ = fs::remove_file("test_0.pnminfo");
+}
+
+#[test]
+fn pnminfo_json_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pnminfo")).arg("--json")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 2\nMAXVAL 65535\nTUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n\x00\x01\x02\x03").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert_eq!("[{\"file\":\"-\",\"count\":1,\"images\":[{\"format\":\"pam\",\"magic\":\"P7\",\"mode\":\"raw\",\
+                \"width\":1,\"height\":1,\"max_val\":65535,\"channels\":2,\"tuple_type\":\"GRAYSCALE_ALPHA\",\
+                \"comments\":[],\"raster_bytes\":4}],\"error\":null}]\n", text);
+}
+
+#[test]
+fn pnminfo_error() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pnminfo")).args(["--json", "does_not_exist.pnminfo"]).output().unwrap();
+    assert_eq!(Some(1), output.status.code());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("\"count\":0"));
+    assert!(!text.contains("\"error\":null"));
+}
+
+#[test]
+fn pnminfo_ascii_json() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pnminfo")).arg("--json")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"P1\n2 1\n1 0\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("\"mode\":\"plain\""));
+    assert!(text.contains("\"raster_bytes\":null"));
+}
+
+#[test]
+fn pnminfo_long_rows() {
+    // The header claims rows far longer than the data, which must not be allocated.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pnminfo"))
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"P7\nWIDTH 4000000000\nHEIGHT 1\nDEPTH 4000\nMAXVAL 65535\nENDHDR\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(Some(1), output.status.code());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("1 image\n"));
+    assert!(text.contains("error: "));
+}
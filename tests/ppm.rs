@@ -1,9 +1,10 @@
+#![allow(clippy::assertions_on_constants)]
+
 extern crate netbpm;
 use netbpm::ppm::{PPMEncoder,PPMDecoder};
-use netbpm::{Mode,BitDepth};
+use netbpm::{Mode,BitDepth,Error};
 use std::fs;
 use std::fs::File;
-use std::error::Error;
 use std::io::prelude::*;
 
 const J:[u8;180] = [255,255,255,   255,255,255,   255,255,255,   255,255,255,   0,0,0,   255,255,255,
@@ -38,13 +39,13 @@ fn test_arrs(size:u32, a:&[u8], b:&[u8]) -> bool {
 
 #[test]
 fn ppm_j_single_ascii() {
-    let mut encoder = PPMEncoder::new("test_0a.ppm");
+    let mut encoder = PPMEncoder::new("test_0a.ppm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PPMDecoder::new("test_0a.ppm");
+    let mut decoder = PPMDecoder::new("test_0a.ppm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -55,13 +56,13 @@ fn ppm_j_single_ascii() {
 
 #[test]
 fn ppm_j_single_binary() {
-    let mut encoder = PPMEncoder::new("test_0b.ppm");
+    let mut encoder = PPMEncoder::new("test_0b.ppm").unwrap();
     let enc_result = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PPMDecoder::new("test_0b.ppm");
+    let mut decoder = PPMDecoder::new("test_0b.ppm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -71,25 +72,31 @@ fn ppm_j_single_binary() {
 }
 
 #[test]
-fn ppm_j_double_ascii_error() {
-    let mut encoder = PPMEncoder::new("test_1a.ppm");
+fn ppm_j_double_ascii() {
+    let mut encoder = PPMEncoder::new("test_1a.ppm").unwrap();
     let enc_result = encoder.save(&J_DOUBLE, 6, 10, Mode::ASCII, BitDepth::SIXTEEN);
     match enc_result {
-        Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Width can not be greater than 70 characters for ascii ppm files."),
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
     }
+    let mut decoder = PPMDecoder::new("test_1a.ppm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(6, image.width);
+    assert_eq!(10, image.height);
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert!(test_arrs(360, &image.dat, &J_DOUBLE));
     let _ = fs::remove_file("test_1a.ppm");
 }
 
 #[test]
 fn ppm_j_double_binary() {
-    let mut encoder = PPMEncoder::new("test_1b.ppm");
+    let mut encoder = PPMEncoder::new("test_1b.ppm").unwrap();
     let enc_result = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
     match enc_result {
         Ok(()) => assert!(true),
         Err(_) => assert!(false),
     }
-    let mut decoder = PPMDecoder::new("test_1b.ppm");
+    let mut decoder = PPMDecoder::new("test_1b.ppm").unwrap();
     let image = decoder.load().unwrap();
     assert_eq!(6, image.width);
     assert_eq!(10, image.height);
@@ -102,11 +109,172 @@ fn ppm_j_double_binary() {
 fn ppm_wrong_filetype_error() {
     let mut file = File::create("file_type.ppm").unwrap();
     let _ = file.write(b"P2\n2 2\n255\n0 255\n255 0");
-    let mut decoder = PPMDecoder::new("file_type.ppm");
+    let mut decoder = PPMDecoder::new("file_type.ppm").unwrap();
     let dec_result = decoder.load();
     match dec_result {
         Ok(_) => assert!(false),
-        Err(e) => assert_eq!(e.description(),"Input file is not a ppm file."),
+        Err(e) => assert_eq!(e.to_string(),"Input file is not a ppm file."),
     }
     let _ = fs::remove_file("file_type.ppm");
 }
+
+#[test]
+fn ppm_ten_bit_binary() {
+    // 2 1, max value 1023
+    let dat:[u8;12] = [0,0, 1,255, 3,255,   3,255, 2,0, 0,0];
+    let mut encoder = PPMEncoder::new("test_2.ppm").unwrap();
+    let enc_result = encoder.save_max_val(&dat, 2, 1, Mode::BINARY, 1023);
+    match enc_result {
+        Ok(()) => assert!(true),
+        Err(_) => assert!(false),
+    }
+    let mut decoder = PPMDecoder::new("test_2.ppm").unwrap();
+    let image = decoder.load().unwrap();
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert_eq!(1023, image.max_val);
+    assert!(test_arrs(12, &image.dat, &dat));
+    let mut decoder = PPMDecoder::new("test_2.ppm").unwrap();
+    let image = decoder.load_rescaled().unwrap();
+    assert_eq!(65535, image.max_val);
+    assert!(test_arrs(12, &image.dat, &[0,0, 127,223, 255,255,   255,255, 128,32, 0,0]));
+    let _ = fs::remove_file("test_2.ppm");
+}
+
+#[test]
+fn ppm_multiple_images() {
+    let mut encoder = PPMEncoder::new("test_3.ppm").unwrap();
+    let _ = encoder.save(&J, 6, 10, Mode::ASCII, BitDepth::EIGHT);
+    let _ = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
+    let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+    let decoder = PPMDecoder::new("test_3.ppm").unwrap();
+    let images:Vec<_> = decoder.map(|i| i.unwrap()).collect();
+    assert_eq!(3, images.len());
+    assert!(test_arrs(180, &images[0].dat, &J));
+    assert_eq!(360, images[1].dat.len());
+    assert!(test_arrs(360, &images[1].dat, &J_DOUBLE));
+    assert_eq!(180, images[2].dat.len());
+    assert!(test_arrs(180, &images[2].dat, &J));
+    let _ = fs::remove_file("test_3.ppm");
+}
+
+#[test]
+fn ppm_multiple_images_bad_second() {
+    let mut file = File::create("test_4.ppm").unwrap();
+    let _ = file.write(b"P3\n1 1\n255\n1 2 3\nP2\n1 1\n255\n4\n");
+    let mut decoder = PPMDecoder::new("test_4.ppm").unwrap();
+    let image = decoder.next().unwrap().unwrap();
+    assert!(test_arrs(3, &image.dat, &[1, 2, 3]));
+    match decoder.next() {
+        Some(Err(e)) => assert_eq!(e.to_string(),"Input file is not a ppm file."),
+        _ => assert!(false),
+    }
+    assert!(decoder.next().is_none());
+    let _ = fs::remove_file("test_4.ppm");
+}
+
+#[test]
+fn ppm_in_memory() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+    }
+    let mut decoder = PPMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(6, image.width);
+    assert!(test_arrs(180, &image.dat, &J));
+}
+
+#[test]
+fn ppm_rows() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save(&J, 6, 10, Mode::BINARY, BitDepth::EIGHT);
+        let _ = encoder.save(&J_DOUBLE, 6, 10, Mode::BINARY, BitDepth::SIXTEEN);
+    }
+    let mut decoder = PPMDecoder::from_reader(&buffer[..]);
+    let rows:Vec<_> = decoder.rows().unwrap().map(|r| r.unwrap()).collect();
+    assert_eq!(10, rows.len());
+    assert_eq!(18, rows[0].len());
+    assert!(test_arrs(18, &rows[6], &J[108..]));
+    let dat:Vec<u8> = decoder.rows().unwrap().flat_map(|r| r.unwrap()).collect();
+    assert!(test_arrs(360, &dat, &J_DOUBLE));
+}
+
+#[test]
+fn ppm_row_writer() {
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let mut writer = encoder.rows(6, 10, Mode::ASCII, BitDepth::SIXTEEN).unwrap();
+        for row in J_DOUBLE.chunks(36) {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+    let mut decoder = PPMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load().unwrap();
+    assert_eq!(BitDepth::SIXTEEN, image.depth);
+    assert!(test_arrs(360, &image.dat, &J_DOUBLE));
+}
+
+#[test]
+fn ppm_u16() {
+    let dat:[u16;6] = [65535,0,0, 300,40000,2];
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PPMEncoder::from_writer(&mut buffer);
+        let _ = encoder.save_u16(&dat, 2, 1, Mode::ASCII, 65535);
+    }
+    let mut decoder = PPMDecoder::from_reader(&buffer[..]);
+    let image = decoder.load_u16().unwrap();
+    assert_eq!(2, image.width);
+    assert_eq!(dat.to_vec(), image.dat);
+}
+
+#[test]
+fn ppm_save_wrong_len() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        match encoder.save(&J[..60], 6, 10, mode, BitDepth::EIGHT) {
+            Err(Error::DimensionMismatch{expected: 180, found: 60}) => {},
+            _ => assert!(false),
+        }
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn ppm_save_sample_over_max_val() {
+    let mut buffer:Vec<u8> = vec![];
+    let mut encoder = PPMEncoder::from_writer(&mut buffer);
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        match encoder.save_max_val(&[1, 2, 3, 4, 5, 200], 2, 1, mode, 199) {
+            Err(Error::SampleOutOfRange{value: 200, max_val: 199}) => {},
+            _ => assert!(false),
+        }
+        match encoder.save_stride(&[0, 1, 0, 2, 3, 233, 9, 9], 1, 1, 8, mode, 1000) {
+            Err(Error::SampleOutOfRange{value: 1001, max_val: 1000}) => {},
+            _ => assert!(false),
+        }
+    }
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn ppm_save_stride() {
+    // Rows of one 16-bit pixel with two bytes of padding.
+    let dat:[u8;14] = [1,0,2,0,3,0, 9,9,
+                       4,0,5,0,6,0];
+    for &mode in &[Mode::ASCII, Mode::BINARY] {
+        let mut buffer:Vec<u8> = vec![];
+        {
+            let mut encoder = PPMEncoder::from_writer(&mut buffer);
+            encoder.save_stride(&dat, 1, 2, 8, mode, 65535).unwrap();
+        }
+        let image = PPMDecoder::from_reader(&buffer[..]).load().unwrap();
+        assert!(test_arrs(12, &image.dat, &[1,0,2,0,3,0, 4,0,5,0,6,0]));
+    }
+}
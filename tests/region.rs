@@ -0,0 +1,196 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pbm::{PBMDecoder,PBMEncoder};
+use netbpm::pgm::{PGMDecoder,PGMEncoder};
+use netbpm::ppm::PPMDecoder;
+use netbpm::{Error,Limits,Mode};
+use std::io;
+use std::io::prelude::*;
+use std::io::{Cursor,SeekFrom};
+
+/// A reader that counts the bytes read from it.
+struct Counting<'a> {
+    inner: Cursor<&'a [u8]>,
+    read: usize,
+}
+
+impl<'a> Read for Counting<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        Ok(n)
+    }
+}
+
+impl<'a> Seek for Counting<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Makes a pgm image where each sample is its column plus its row times 16.
+fn pgm_grid(width: u32, height: u32) -> Vec<u8> {
+    let dat:Vec<u8> = (0..height).flat_map(|y| (0..width).map(move |x| (x + y * 16) as u8)).collect();
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        encoder.save_max_val(&dat, width, height, Mode::BINARY, 255).unwrap();
+    }
+    buffer
+}
+
+#[test]
+fn region_pgm() {
+    let buffer = pgm_grid(8, 6);
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+    let region = decoder.decode_region(2, 3, 3, 2).unwrap();
+    assert_eq!(region.width, 3);
+    assert_eq!(region.height, 2);
+    assert_eq!(region.max_val, 255);
+    assert_eq!(region.dat, vec![0x32, 0x33, 0x34, 0x42, 0x43, 0x44]);
+}
+
+#[test]
+fn region_whole_image() {
+    let buffer = pgm_grid(5, 4);
+    let whole = PGMDecoder::from_reader(&buffer[..]).load().unwrap();
+    let region = PGMDecoder::from_reader(Cursor::new(&buffer[..])).decode_region(0, 0, 5, 4).unwrap();
+    assert_eq!(whole.dat, region.dat);
+}
+
+#[test]
+fn region_ppm_sixteen() {
+    let dat:&[u8] = b"P6\n2 2\n1000\n\x00\x01\x00\x02\x00\x03\x00\x04\x00\x05\x00\x06\
+                      \x01\x01\x01\x02\x01\x03\x01\x04\x01\x05\x01\x06";
+    let mut decoder = PPMDecoder::from_reader(Cursor::new(dat));
+    let region = decoder.decode_region(1, 1, 1, 1).unwrap();
+    assert_eq!(region.dat, vec![1, 4, 1, 5, 1, 6]);
+}
+
+#[test]
+fn region_pbm_bit_offsets() {
+    let width = 21;
+    let height = 3;
+    let dat:Vec<u8> = (0..width * height).map(|i| ((i * 7 + i / 5) % 3 == 0) as u8).collect();
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PBMEncoder::from_writer(&mut buffer);
+        encoder.save(&dat, width, height, Mode::BINARY).unwrap();
+    }
+    for x in 0..width {
+        for w in 0..(width - x) + 1 {
+            let mut decoder = PBMDecoder::from_reader(Cursor::new(&buffer[..]));
+            let region = decoder.decode_region(x, 1, w, 2).unwrap();
+            let expected:Vec<u8> = (1..3).flat_map(|y| {
+                let start = (y * width + x) as usize;
+                dat[start..start + w as usize].to_vec()
+            }).collect();
+            assert_eq!(region.dat, expected);
+        }
+    }
+}
+
+#[test]
+fn region_reads_only_needed_rows() {
+    let buffer = pgm_grid(4096, 64);
+    let mut reader = Counting{inner: Cursor::new(&buffer[..]), read: 0};
+    {
+        let mut decoder = PGMDecoder::from_reader(&mut reader);
+        let region = decoder.decode_region(4000, 60, 4, 2).unwrap();
+        assert_eq!(region.dat, vec![0x60, 0x61, 0x62, 0x63, 0x70, 0x71, 0x72, 0x73]);
+    }
+    // Only the first block read for the header, not the whole 256 KB raster.
+    assert!(reader.read < 16384);
+}
+
+#[test]
+fn region_then_next_image() {
+    let mut buffer = pgm_grid(4, 4);
+    buffer.extend_from_slice(b"P5\n1 1\n255\n\x2a");
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+    decoder.decode_region(1, 1, 2, 2).unwrap();
+    assert_eq!(decoder.load().unwrap().dat, vec![42]);
+    match decoder.load() {
+        Err(Error::NoMoreImages) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn region_outside_image() {
+    let buffer = pgm_grid(4, 4);
+    for &(x, y, w, h) in &[(3, 0, 2, 1), (0, 4, 1, 1), (u32::MAX, 0, 2, 1), (0, 1, 1, u32::MAX)] {
+        let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+        match decoder.decode_region(x, y, w, h) {
+            Err(Error::InvalidParameter(_)) => {},
+            _ => assert!(false),
+        }
+    }
+}
+
+#[test]
+fn region_rejected_then_next_image() {
+    let mut buffer = pgm_grid(2, 1);
+    buffer.extend_from_slice(b"P5\n1 1\n255\n\x2a");
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+    match decoder.decode_region(5, 0, 1, 1) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+    decoder.set_limits(Limits{max_pixels: 1, ..Limits::default()});
+    match decoder.decode_region(0, 0, 2, 1) {
+        Err(Error::LimitExceeded{limit: "pixel count", value: 2, max: 1}) => {},
+        _ => assert!(false),
+    }
+    decoder.set_limits(Limits::default());
+    assert_eq!(decoder.decode_region(1, 0, 1, 1).unwrap().dat, vec![1]);
+    assert_eq!(decoder.load().unwrap().dat, vec![42]);
+}
+
+#[test]
+fn region_ascii_image() {
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&b"P2\n2 1\n255\n1 2\n"[..]));
+    match decoder.decode_region(0, 0, 1, 1) {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn region_truncated() {
+    let buffer = pgm_grid(4, 4);
+    let buffer = &buffer[..buffer.len() - 3];
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(buffer));
+    assert_eq!(decoder.decode_region(0, 0, 4, 3).unwrap().dat.len(), 12);
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(buffer));
+    match decoder.decode_region(0, 3, 2, 1) {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn region_limits() {
+    let buffer = pgm_grid(64, 64);
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+    decoder.set_limits(Limits{max_pixels: 100, ..Limits::default()});
+    assert_eq!(decoder.decode_region(10, 10, 10, 10).unwrap().dat.len(), 100);
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&buffer[..]));
+    decoder.set_limits(Limits{max_pixels: 100, ..Limits::default()});
+    match decoder.decode_region(0, 0, 11, 10) {
+        Err(Error::LimitExceeded{limit: "pixel count", value: 110, max: 100}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn region_sample_out_of_range() {
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&b"P5\n2 1\n100\n\x01\xc8"[..]));
+    assert_eq!(decoder.decode_region(0, 0, 1, 1).unwrap().dat, vec![1]);
+    let mut decoder = PGMDecoder::from_reader(Cursor::new(&b"P5\n2 1\n100\n\x01\xc8"[..]));
+    match decoder.decode_region(1, 0, 1, 1) {
+        Err(Error::SampleOutOfRange{value: 200, max_val: 100}) => {},
+        _ => assert!(false),
+    }
+}
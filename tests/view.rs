@@ -0,0 +1,83 @@
+#![allow(clippy::assertions_on_constants)]
+
+extern crate netbpm;
+use netbpm::pgm::{self,PGMDecoder,PGMEncoder};
+use netbpm::ppm::{self,PPMDecoder};
+use netbpm::{BitDepth,Comment,Error,Mode};
+
+#[test]
+fn view_matches_load() {
+    let dat:Vec<u8> = (0..48).collect();
+    let mut buffer:Vec<u8> = vec![];
+    {
+        let mut encoder = PGMEncoder::from_writer(&mut buffer);
+        encoder.set_comments(&[Comment{text: "scan 12".to_string(), position: 0}]);
+        encoder.save(&dat, 8, 6, Mode::BINARY, BitDepth::EIGHT).unwrap();
+    }
+    let image = PGMDecoder::from_reader(&buffer[..]).load().unwrap();
+    let view = pgm::view(&buffer).unwrap();
+    assert_eq!(view.width, image.width);
+    assert_eq!(view.height, image.height);
+    assert_eq!(view.depth, image.depth);
+    assert_eq!(view.max_val, image.max_val);
+    assert_eq!(view.comments, image.comments);
+    assert_eq!(view.dat, &image.dat[..]);
+    assert_eq!(view.to_image().dat, image.dat);
+    assert_eq!(image.view().dat, view.dat);
+}
+
+#[test]
+fn view_borrows_raster() {
+    let file:&[u8] = b"P6\n1 1\n65535\n\x01\x02\x03\x04\x05\x06";
+    let view = ppm::view(file).unwrap();
+    assert_eq!(view.depth, BitDepth::SIXTEEN);
+    assert_eq!(view.dat.as_ptr(), file[13..].as_ptr());
+    assert_eq!(view.dat, &file[13..]);
+    assert_eq!(PPMDecoder::from_reader(file).load().unwrap().dat, view.dat.to_vec());
+}
+
+#[test]
+fn view_ignores_following_data() {
+    let file:&[u8] = b"P5\n2 1\n255\n\x01\x02P5\n1 1\n255\n\x03";
+    assert_eq!(pgm::view(file).unwrap().dat, &[1, 2]);
+}
+
+#[test]
+fn view_wrong_format() {
+    match pgm::view(b"P6\n1 1\n255\n\x00\x00\x00") {
+        Err(Error::BadMagicNumber{expected: Some("pgm")}) => {},
+        _ => assert!(false),
+    }
+    match ppm::view(b"P5\n1 1\n255\n\x00") {
+        Err(Error::BadMagicNumber{expected: Some("ppm")}) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn view_ascii() {
+    match pgm::view(b"P2\n1 1\n255\n0\n") {
+        Err(Error::InvalidParameter(_)) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn view_truncated() {
+    match pgm::view(b"P5\n2 2\n255\n\x00\x00\x00") {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+    match pgm::view(b"P5\n2 2\n25") {
+        Err(Error::Truncated) => {},
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn view_sample_out_of_range() {
+    match pgm::view(b"P5\n2 1\n1000\n\x03\xe8\x03\xe9") {
+        Err(Error::SampleOutOfRange{value: 1001, max_val: 1000}) => {},
+        _ => assert!(false),
+    }
+}